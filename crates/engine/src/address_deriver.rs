@@ -0,0 +1,58 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Types implementing this trait derive the addresses of instantiated contracts.
+///
+/// This allows to test contracts against the address derivation of runtimes which
+/// differ from the `contracts` pallet.
+pub trait AddressDeriver {
+    /// Returns the address of the contract instantiated by `deployer` from
+    /// `code_hash` with the constructor `input` and `salt`.
+    ///
+    /// The engine truncates the returned address to the address length of the
+    /// chain.
+    fn derive(
+        &self,
+        code_hash: &[u8],
+        input: &[u8],
+        salt: &[u8],
+        deployer: &[u8],
+    ) -> Vec<u8>;
+}
+
+/// The address derivation of the `contracts` pallet.
+///
+/// Hashes the deployer, the code hash and the SCALE encoded input and salt with
+/// BLAKE2 256-bit.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Blake2AddressDeriver;
+
+impl AddressDeriver for Blake2AddressDeriver {
+    fn derive(
+        &self,
+        code_hash: &[u8],
+        input: &[u8],
+        salt: &[u8],
+        deployer: &[u8],
+    ) -> Vec<u8> {
+        let mut entropy = b"contract_addr_v1".to_vec();
+        entropy.extend_from_slice(deployer);
+        entropy.extend_from_slice(code_hash);
+        entropy.extend(scale::Encode::encode(input));
+        entropy.extend(scale::Encode::encode(salt));
+        let mut address = [0; 32];
+        crate::hashing::blake2b_256(&entropy[..], &mut address);
+        address.to_vec()
+    }
+}
@@ -14,9 +14,12 @@
 
 use super::Error;
 use derive_more::From;
-use std::collections::{
-    hash_map::Entry,
-    HashMap,
+use std::{
+    collections::{
+        hash_map::Entry,
+        HashMap,
+    },
+    marker::PhantomData,
 };
 
 /// Chain extension registry.
@@ -25,6 +28,11 @@ use std::collections::{
 pub struct ChainExtensionHandler {
     /// The currently registered runtime call handler.
     registered: HashMap<ExtensionId, Box<dyn ChainExtension>>,
+    /// The registered whole chain extensions, keyed by their extension ID.
+    extensions: HashMap<u16, Box<dyn Extension>>,
+    /// The chain extension object handling the calls no method or extension is
+    /// registered for.
+    object: Option<Box<dyn ChainExtensionObject>>,
     /// The output buffer used and reused for chain extension method call results.
     output: Vec<u8>,
 }
@@ -54,6 +62,30 @@ pub trait ChainExtension {
     fn call(&mut self, input: &[u8], output: &mut Vec<u8>) -> u32;
 }
 
+/// Types implementing this trait can be used as a whole chain extension, i.e. a
+/// group of chain extension methods under a common extension ID.
+///
+/// The function ID passed to `call_chain_extension` carries the extension ID in
+/// its high 16 bits and the ID of the method within the extension in its low 16
+/// bits.
+pub trait Extension {
+    /// Calls the method `func_id` of the chain extension with the given input.
+    ///
+    /// Returns an error code and may fill the `output` buffer with a SCALE encoded
+    /// result.
+    #[allow(clippy::ptr_arg)]
+    fn call(&mut self, func_id: u16, input: &[u8], output: &mut Vec<u8>) -> u32;
+}
+
+/// Types implementing this trait can be used as a chain extension handling any
+/// function ID, e.g. a stateful stub implementing several methods at once.
+pub trait ChainExtensionObject {
+    /// Calls the chain extension method `func_id` with the given input.
+    ///
+    /// Returns an error code and the SCALE encoded result.
+    fn call(&mut self, func_id: u32, input: &[u8]) -> (u32, Vec<u8>);
+}
+
 impl Default for ChainExtensionHandler {
     fn default() -> Self {
         ChainExtensionHandler::new()
@@ -67,6 +99,8 @@ impl ChainExtensionHandler {
     pub fn new() -> Self {
         Self {
             registered: HashMap::new(),
+            extensions: HashMap::new(),
+            object: None,
             output: Vec::new(),
         }
     }
@@ -74,6 +108,8 @@ impl ChainExtensionHandler {
     /// Resets the chain extension handler to uninitialized state.
     pub fn reset(&mut self) {
         self.registered.clear();
+        self.extensions.clear();
+        self.object = None;
         self.output.clear();
     }
 
@@ -84,6 +120,44 @@ impl ChainExtensionHandler {
             .insert(ExtensionId::from(func_id), extension);
     }
 
+    /// Register a whole chain extension under `ext_id`.
+    ///
+    /// Chain extension methods registered for a full function ID via
+    /// [`ChainExtensionHandler::register`] take precedence over the extension.
+    pub fn register_extension<E>(&mut self, ext_id: u16, extension: E)
+    where
+        E: Extension + 'static,
+    {
+        self.extensions.insert(ext_id, Box::new(extension));
+    }
+
+    /// Register the chain extension object which handles all calls for which
+    /// neither a chain extension method nor a whole chain extension is
+    /// registered.
+    ///
+    /// Replaces a previously registered chain extension object.
+    pub fn register_object(&mut self, object: Box<dyn ChainExtensionObject>) {
+        self.object = Some(object);
+    }
+
+    /// Register a new chain extension method from a closure operating on decoded
+    /// values.
+    ///
+    /// Upon a call the input is decoded into `I` and passed to `f`, the returned
+    /// `O` is encoded into the output alongside the returned status code.
+    pub fn register_typed<I, O, F>(&mut self, func_id: u32, f: F)
+    where
+        I: scale::Decode + 'static,
+        O: scale::Encode + 'static,
+        F: FnMut(I) -> (u32, O) + 'static,
+    {
+        self.register(Box::new(TypedChainExtension {
+            func_id,
+            f,
+            marker: PhantomData,
+        }))
+    }
+
     /// Evaluates the chain extension with the given parameters.
     ///
     /// Upon success returns the values returned by the evaluated chain extension.
@@ -95,7 +169,58 @@ impl ChainExtensionHandler {
                 let status_code = occupied.into_mut().call(input, &mut self.output);
                 Ok((status_code, &mut self.output))
             }
-            Entry::Vacant(_vacant) => Err(Error::UnregisteredChainExtension),
+            Entry::Vacant(_vacant) => {
+                let ext_id = (func_id >> 16) as u16;
+                let method_id = (func_id & 0xFFFF) as u16;
+                let status_code =
+                    match (self.extensions.get_mut(&ext_id), &mut self.object) {
+                        (Some(extension), _) => {
+                            extension.call(method_id, input, &mut self.output)
+                        }
+                        (None, Some(object)) => {
+                            let (status_code, output) = object.call(func_id, input);
+                            self.output = output;
+                            status_code
+                        }
+                        (None, None) => return Err(Error::UnregisteredChainExtension),
+                    };
+                Ok((status_code, &mut self.output))
+            }
         }
     }
 }
+
+/// A chain extension method which decodes its input and encodes its output.
+struct TypedChainExtension<I, O, F> {
+    /// The function ID of the chain extension method.
+    func_id: u32,
+    /// The implementation of the chain extension method.
+    f: F,
+    marker: PhantomData<fn(I) -> O>,
+}
+
+impl<I, O, F> ChainExtension for TypedChainExtension<I, O, F>
+where
+    I: scale::Decode,
+    O: scale::Encode,
+    F: FnMut(I) -> (u32, O),
+{
+    fn func_id(&self) -> u32 {
+        self.func_id
+    }
+
+    fn call(&mut self, mut input: &[u8], output: &mut Vec<u8>) -> u32 {
+        // The engine passes the encoded input as SCALE encoded byte vector.
+        let encoded_input = <Vec<u8> as scale::Decode>::decode(&mut input)
+            .and_then(|encoded| I::decode(&mut &encoded[..]))
+            .unwrap_or_else(|err| {
+                panic!(
+                    "could not decode input of chain extension method {}: {err}",
+                    self.func_id
+                )
+            });
+        let (status_code, result) = (self.f)(encoded_input);
+        scale::Encode::encode_to(&result, output);
+        status_code
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::ext::Engine;
+use std::collections::HashMap;
+
+/// The entry points of a contract known to the engine.
+///
+/// The off-chain engine cannot execute Wasm, instead it invokes these functions
+/// directly. They read their input from and write their output into the
+/// currently executing `ExecContext` of the supplied engine.
+#[derive(Clone, Copy)]
+pub struct Contract {
+    /// Executes the constructor of the contract.
+    pub deploy: fn(&mut Engine),
+    /// Executes a message of the contract.
+    pub call: fn(&mut Engine),
+}
+
+/// The contracts known to the engine.
+#[derive(Default, Clone)]
+pub struct ContractStorage {
+    /// The code of the deployed contracts, keyed by their code hash.
+    pub deployed: HashMap<Vec<u8>, Contract>,
+    /// The number of currently active calls into each contract account.
+    pub entrance_count: HashMap<Vec<u8>, u32>,
+    /// Whether the contract account may be re-entered by its ongoing call.
+    pub allow_reentry: HashMap<Vec<u8>, bool>,
+}
+
+impl ContractStorage {
+    /// Creates a new contract storage.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the contract code registered under `code_hash`, if any.
+    pub fn get(&self, code_hash: &[u8]) -> Option<Contract> {
+        self.deployed.get(code_hash).copied()
+    }
+
+    /// Returns the number of currently active calls into `account_id`.
+    pub fn entrance_count(&self, account_id: &[u8]) -> u32 {
+        self.entrance_count.get(account_id).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of currently active contract frames.
+    pub fn call_depth(&self) -> u32 {
+        self.entrance_count.values().sum()
+    }
+
+    /// Removes all registered contracts and reentrancy bookkeeping.
+    pub fn clear(&mut self) {
+        self.deployed.clear();
+        self.entrance_count.clear();
+        self.allow_reentry.clear();
+    }
+}
@@ -12,12 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::types::Balance;
+use crate::types::{
+    AccountId,
+    Balance,
+};
 use scale::KeyedVec;
-use std::collections::HashMap;
+use std::collections::{
+    BTreeSet,
+    HashMap,
+};
 
 const BALANCE_OF: &[u8] = b"balance:";
 const STORAGE_OF: &[u8] = b"contract-storage:";
+const CODE_HASH_OF: &[u8] = b"code-hash:";
+const RESERVED_BALANCE_OF: &[u8] = b"reserved-balance:";
+const STORAGE_DEPOSIT_OF: &[u8] = b"storage-deposit:";
+const NONCE_OF: &[u8] = b"nonce:";
 
 /// Returns the database key under which to find the balance for account `who`.
 pub fn balance_of_key(who: &[u8]) -> [u8; 32] {
@@ -35,6 +45,53 @@ pub fn storage_of_contract_key(who: &[u8], key: &[u8]) -> [u8; 32] {
     hashed_key
 }
 
+/// Returns the database key under which to find the code hash for account `who`.
+pub fn code_hash_of_key(who: &[u8]) -> [u8; 32] {
+    let keyed = who.to_vec().to_keyed_vec(CODE_HASH_OF);
+    let mut hashed_key: [u8; 32] = [0; 32];
+    super::hashing::blake2b_256(&keyed[..], &mut hashed_key);
+    hashed_key
+}
+
+/// Returns the database key under which to find the reserved balance for account
+/// `who`.
+pub fn reserved_balance_of_key(who: &[u8]) -> [u8; 32] {
+    let keyed = who.to_vec().to_keyed_vec(RESERVED_BALANCE_OF);
+    let mut hashed_key: [u8; 32] = [0; 32];
+    super::hashing::blake2b_256(&keyed[..], &mut hashed_key);
+    hashed_key
+}
+
+/// Returns the database key under which to find the storage deposit held for the
+/// contract `who`.
+pub fn storage_deposit_of_key(who: &[u8]) -> [u8; 32] {
+    let keyed = who.to_vec().to_keyed_vec(STORAGE_DEPOSIT_OF);
+    let mut hashed_key: [u8; 32] = [0; 32];
+    super::hashing::blake2b_256(&keyed[..], &mut hashed_key);
+    hashed_key
+}
+
+/// Returns the database key under which to find the nonce of account `who`.
+pub fn nonce_of_key(who: &[u8]) -> [u8; 32] {
+    let keyed = who.to_vec().to_keyed_vec(NONCE_OF);
+    let mut hashed_key: [u8; 32] = [0; 32];
+    super::hashing::blake2b_256(&keyed[..], &mut hashed_key);
+    hashed_key
+}
+
+/// Identifies a snapshot of the database taken via [`Database::snapshot`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotId(usize);
+
+/// The contents of the database at the time a snapshot was taken.
+type Snapshot = (
+    HashMap<Vec<u8>, Vec<u8>>,
+    HashMap<Vec<u8>, BTreeSet<Vec<u8>>>,
+    HashMap<Vec<u8>, u64>,
+    BTreeSet<Vec<u8>>,
+    Balance,
+);
+
 /// The chain database.
 ///
 /// Everything is stored in here: accounts, balances, contract storage, etc..
@@ -42,6 +99,19 @@ pub fn storage_of_contract_key(who: &[u8], key: &[u8]) -> [u8; 32] {
 #[derive(Default)]
 pub struct Database {
     hmap: HashMap<Vec<u8>, Vec<u8>>,
+    /// The storage keys in use by each contract, sorted to allow for deterministic
+    /// iteration.
+    contract_storage_keys: HashMap<Vec<u8>, BTreeSet<Vec<u8>>>,
+    /// The total length in bytes of the values stored by each contract.
+    contract_storage_bytes: HashMap<Vec<u8>, u64>,
+    /// The accounts which have a code hash set, sorted to allow for deterministic
+    /// iteration.
+    contract_accounts: BTreeSet<Vec<u8>>,
+    /// The snapshots which have been taken and not yet restored or released.
+    snapshots: Vec<Snapshot>,
+    /// The sum of the free and reserved balances and the storage deposits of all
+    /// accounts.
+    total_issuance: Balance,
 }
 
 impl Database {
@@ -49,6 +119,11 @@ impl Database {
     pub fn new() -> Self {
         Database {
             hmap: HashMap::new(),
+            contract_storage_keys: HashMap::new(),
+            contract_storage_bytes: HashMap::new(),
+            contract_accounts: BTreeSet::new(),
+            snapshots: Vec::new(),
+            total_issuance: 0,
         }
     }
 
@@ -70,7 +145,71 @@ impl Database {
         key: &[u8],
     ) -> Option<&Vec<u8>> {
         let hashed_key = storage_of_contract_key(account_id, key);
-        self.hmap.get(&hashed_key.to_vec())
+        self.hmap.get(hashed_key.as_slice())
+    }
+
+    /// Returns the length of the value stored in the contract storage of
+    /// `account_id` at storage key `key`, without copying the value.
+    pub fn contract_storage_len(&self, account_id: &[u8], key: &[u8]) -> Option<u32> {
+        self.get_from_contract_storage(account_id, key)
+            .map(|value| value.len() as u32)
+    }
+
+    /// Returns the total length in bytes of the values stored in the contract
+    /// storage of `account_id`.
+    pub fn contract_storage_bytes(&self, account_id: &[u8]) -> u64 {
+        self.contract_storage_bytes
+            .get(account_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Accounts for the value of length `old_len` in the contract storage of
+    /// `account_id` being replaced with one of length `new_len`.
+    fn adjust_contract_storage_bytes(
+        &mut self,
+        account_id: &[u8],
+        old_len: usize,
+        new_len: usize,
+    ) {
+        if old_len == new_len {
+            return
+        }
+        let bytes =
+            self.contract_storage_bytes(account_id) - old_len as u64 + new_len as u64;
+        if bytes == 0 {
+            self.contract_storage_bytes.remove(account_id);
+        } else {
+            self.contract_storage_bytes
+                .insert(account_id.to_vec(), bytes);
+        }
+    }
+
+    /// Calls `f` with a mutable reference to the value stored in the contract
+    /// storage of `account_id` at storage key `key`, or with `None` if no value is
+    /// stored there.
+    ///
+    /// Allows updating a value in place, without copying it out and back in.
+    pub fn update_contract_storage<F, R>(
+        &mut self,
+        account_id: &[u8],
+        key: &[u8],
+        f: F,
+    ) -> R
+    where
+        F: FnOnce(Option<&mut Vec<u8>>) -> R,
+    {
+        let hashed_key = storage_of_contract_key(account_id, key);
+        let (old_len, new_len, result) = match self.hmap.get_mut(hashed_key.as_slice()) {
+            Some(value) => {
+                let old_len = value.len();
+                let result = f(Some(&mut *value));
+                (old_len, value.len(), result)
+            }
+            None => (0, 0, f(None)),
+        };
+        self.adjust_contract_storage_bytes(account_id, old_len, new_len);
+        result
     }
 
     /// Inserts `value` into the contract storage of `account_id` at storage key `key`.
@@ -81,7 +220,15 @@ impl Database {
         value: Vec<u8>,
     ) -> Option<Vec<u8>> {
         let hashed_key = storage_of_contract_key(account_id, key);
-        self.hmap.insert(hashed_key.to_vec(), value)
+        self.contract_storage_keys
+            .entry(account_id.to_vec())
+            .or_default()
+            .insert(key.to_vec());
+        let new_len = value.len();
+        let old_value = self.hmap.insert(hashed_key.to_vec(), value);
+        let old_len = old_value.as_ref().map_or(0, Vec::len);
+        self.adjust_contract_storage_bytes(account_id, old_len, new_len);
+        old_value
     }
 
     /// Removes the value at the contract storage of `account_id` at storage key `key`.
@@ -91,7 +238,65 @@ impl Database {
         key: &[u8],
     ) -> Option<Vec<u8>> {
         let hashed_key = storage_of_contract_key(account_id, key);
-        self.hmap.remove(&hashed_key.to_vec())
+        if let Some(keys) = self.contract_storage_keys.get_mut(account_id) {
+            keys.remove(key);
+            if keys.is_empty() {
+                self.contract_storage_keys.remove(account_id);
+            }
+        }
+        let old_value = self.hmap.remove(hashed_key.as_slice());
+        let old_len = old_value.as_ref().map_or(0, Vec::len);
+        self.adjust_contract_storage_bytes(account_id, old_len, 0);
+        old_value
+    }
+
+    /// Removes all values at the contract storage of `account_id` whose storage
+    /// key starts with `prefix`.
+    ///
+    /// Returns the removed `(key, value)` pairs in ascending order of their keys.
+    pub fn remove_contract_storage_prefix(
+        &mut self,
+        account_id: &[u8],
+        prefix: &[u8],
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let keys: Vec<Vec<u8>> = self
+            .contract_storage_keys
+            .get(account_id)
+            .map(|keys| {
+                keys.range(prefix.to_vec()..)
+                    .take_while(|key| key.starts_with(prefix))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        keys.into_iter()
+            .map(|key| {
+                let value = self
+                    .remove_contract_storage(account_id, &key)
+                    .expect("the value must exist since its key is in use");
+                (key, value)
+            })
+            .collect()
+    }
+
+    /// Returns an iterator over the contract storage of `account_id`.
+    ///
+    /// The entries are yielded as `(key, value)` pairs sorted by their key.
+    pub fn iter_contract_storage<'a>(
+        &'a self,
+        account_id: &[u8],
+    ) -> impl Iterator<Item = (&'a Vec<u8>, &'a Vec<u8>)> + 'a {
+        let account_id = account_id.to_vec();
+        self.contract_storage_keys
+            .get(&account_id)
+            .into_iter()
+            .flatten()
+            .map(move |key| {
+                let value = self
+                    .get_from_contract_storage(&account_id, key)
+                    .expect("indexed contract storage key must exist");
+                (key, value)
+            })
     }
 
     /// Removes a key from the storage, returning the value at the key if the key
@@ -108,6 +313,73 @@ impl Database {
     /// Clears the database, removing all key-value pairs.
     pub fn clear(&mut self) {
         self.hmap.clear();
+        self.contract_storage_keys.clear();
+        self.contract_storage_bytes.clear();
+        self.contract_accounts.clear();
+        self.snapshots.clear();
+        self.total_issuance = 0;
+    }
+
+    /// Takes a snapshot of all balances and contract storage.
+    ///
+    /// The returned id can be used to [`restore`][`Database::restore`] the database
+    /// to its current state or to [`release`][`Database::release`] the snapshot.
+    pub fn snapshot(&mut self) -> SnapshotId {
+        self.snapshots.push((
+            self.hmap.clone(),
+            self.contract_storage_keys.clone(),
+            self.contract_storage_bytes.clone(),
+            self.contract_accounts.clone(),
+            self.total_issuance,
+        ));
+        SnapshotId(self.snapshots.len() - 1)
+    }
+
+    /// Restores the database to the state it had when the snapshot `id` was taken.
+    ///
+    /// All snapshots taken after `id` are discarded as well.
+    ///
+    /// # Panics
+    ///
+    /// If no snapshot `id` exists.
+    pub fn restore(&mut self, id: SnapshotId) {
+        assert!(
+            id.0 < self.snapshots.len(),
+            "no snapshot with id {id:?} exists"
+        );
+        let (
+            hmap,
+            contract_storage_keys,
+            contract_storage_bytes,
+            contract_accounts,
+            total_issuance,
+        ) = self.snapshots.swap_remove(id.0);
+        self.snapshots.truncate(id.0);
+        self.hmap = hmap;
+        self.contract_storage_keys = contract_storage_keys;
+        self.contract_storage_bytes = contract_storage_bytes;
+        self.contract_accounts = contract_accounts;
+        self.total_issuance = total_issuance;
+    }
+
+    /// Discards the snapshot `id` and all snapshots taken after it, keeping the
+    /// current state of the database.
+    pub fn release(&mut self, id: SnapshotId) {
+        self.snapshots.truncate(id.0);
+    }
+
+    /// Returns a digest of the whole state of the database, i.e. of all balances,
+    /// contract storage, code hashes and nonces.
+    ///
+    /// The entries are hashed sorted by their key, so two databases with the same
+    /// contents have the same digest regardless of the order of their writes.
+    pub fn state_digest(&self) -> [u8; 32] {
+        let mut entries = self.hmap.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        let encoded = scale::Encode::encode(&entries);
+        let mut digest = [0; 32];
+        super::hashing::blake2b_256(&encoded[..], &mut digest);
+        digest
     }
 
     /// Returns the balance of `account_id`, if available.
@@ -121,6 +393,8 @@ impl Database {
 
     /// Sets the balance of `account_id` to `new_balance`.
     pub fn set_balance(&mut self, account_id: &[u8], new_balance: Balance) {
+        let old_balance = self.get_balance(account_id).unwrap_or_default();
+        self.adjust_total_issuance(old_balance, new_balance);
         let hashed_key = balance_of_key(account_id);
         let encoded_balance = scale::Encode::encode(&new_balance);
         self.hmap
@@ -128,11 +402,160 @@ impl Database {
             .and_modify(|v| *v = encoded_balance.clone())
             .or_insert(encoded_balance);
     }
+
+    /// Returns the reserved balance of `account_id`, if available.
+    pub fn get_reserved_balance(&self, account_id: &[u8]) -> Option<Balance> {
+        let hashed_key = reserved_balance_of_key(account_id);
+        self.get(&hashed_key).map(|encoded_balance| {
+            scale::Decode::decode(&mut &encoded_balance[..])
+                .expect("unable to decode reserved balance from database")
+        })
+    }
+
+    /// Sets the reserved balance of `account_id` to `new_balance`.
+    pub fn set_reserved_balance(&mut self, account_id: &[u8], new_balance: Balance) {
+        let old_balance = self.get_reserved_balance(account_id).unwrap_or_default();
+        self.adjust_total_issuance(old_balance, new_balance);
+        let hashed_key = reserved_balance_of_key(account_id);
+        self.hmap
+            .insert(hashed_key.to_vec(), scale::Encode::encode(&new_balance));
+    }
+
+    /// Returns the storage deposit held for the contract `account_id`, if available.
+    pub fn get_storage_deposit(&self, account_id: &[u8]) -> Option<Balance> {
+        let hashed_key = storage_deposit_of_key(account_id);
+        self.get(&hashed_key).map(|encoded_deposit| {
+            scale::Decode::decode(&mut &encoded_deposit[..])
+                .expect("unable to decode storage deposit from database")
+        })
+    }
+
+    /// Sets the storage deposit held for the contract `account_id` to `new_deposit`.
+    pub fn set_storage_deposit(&mut self, account_id: &[u8], new_deposit: Balance) {
+        let old_deposit = self.get_storage_deposit(account_id).unwrap_or_default();
+        self.adjust_total_issuance(old_deposit, new_deposit);
+        let hashed_key = storage_deposit_of_key(account_id);
+        self.hmap
+            .insert(hashed_key.to_vec(), scale::Encode::encode(&new_deposit));
+    }
+
+    /// Returns the nonce of `account_id`, `0` if it has never been incremented.
+    pub fn nonce(&self, account_id: &[u8]) -> u64 {
+        let hashed_key = nonce_of_key(account_id);
+        self.get(&hashed_key)
+            .map(|encoded_nonce| {
+                scale::Decode::decode(&mut &encoded_nonce[..])
+                    .expect("unable to decode nonce from database")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Increments the nonce of `account_id` by one.
+    pub fn increment_nonce(&mut self, account_id: &[u8]) {
+        let nonce = self.nonce(account_id).saturating_add(1);
+        let hashed_key = nonce_of_key(account_id);
+        self.hmap
+            .insert(hashed_key.to_vec(), scale::Encode::encode(&nonce));
+    }
+
+    /// Returns the sum of the free and reserved balances and the storage deposits
+    /// of all accounts.
+    pub fn total_issuance(&self) -> Balance {
+        self.total_issuance
+    }
+
+    /// Increases the free balance of `account_id` by `amount`, creating it.
+    pub fn mint(&mut self, account_id: &[u8], amount: Balance) {
+        let balance = self.get_balance(account_id).unwrap_or_default();
+        self.set_balance(account_id, balance.saturating_add(amount));
+    }
+
+    /// Decreases the free balance of `account_id` by `amount`, destroying it.
+    ///
+    /// Returns `None` and leaves the balance untouched if it is smaller than
+    /// `amount`.
+    pub fn burn(&mut self, account_id: &[u8], amount: Balance) -> Option<Balance> {
+        let balance = self.get_balance(account_id).unwrap_or_default();
+        let new_balance = balance.checked_sub(amount)?;
+        self.set_balance(account_id, new_balance);
+        Some(new_balance)
+    }
+
+    /// Accounts for a balance changing from `old_balance` to `new_balance` in the
+    /// total issuance.
+    fn adjust_total_issuance(&mut self, old_balance: Balance, new_balance: Balance) {
+        self.total_issuance = self
+            .total_issuance
+            .saturating_sub(old_balance)
+            .saturating_add(new_balance);
+    }
+
+    /// Returns the code hash of the contract at `account_id`, if available.
+    pub fn get_code_hash(&self, account_id: &[u8]) -> Option<Vec<u8>> {
+        let hashed_key = code_hash_of_key(account_id);
+        self.get(&hashed_key).cloned()
+    }
+
+    /// Sets the code hash of the contract at `account_id` to `code_hash`.
+    pub fn set_code_hash(&mut self, account_id: &[u8], code_hash: &[u8]) {
+        let hashed_key = code_hash_of_key(account_id);
+        self.hmap.insert(hashed_key.to_vec(), code_hash.to_vec());
+        self.contract_accounts.insert(account_id.to_vec());
+    }
+
+    /// Removes the code hash and the whole storage of the contract at `account_id`.
+    ///
+    /// Returns the removed `(key, value)` pairs of the storage.
+    pub fn remove_contract(&mut self, account_id: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let hashed_key = code_hash_of_key(account_id);
+        self.hmap.remove(hashed_key.as_slice());
+        self.contract_accounts.remove(account_id);
+        self.remove_contract_storage_prefix(account_id, &[])
+    }
+
+    /// Removes everything stored for `account_id`: its free and reserved balance,
+    /// the storage deposit held for it, its nonce, its code hash and its storage.
+    ///
+    /// Returns the removed `(key, value)` pairs of the storage.
+    pub fn clear_account(&mut self, account_id: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let held = self
+            .get_balance(account_id)
+            .unwrap_or_default()
+            .saturating_add(self.get_reserved_balance(account_id).unwrap_or_default())
+            .saturating_add(self.get_storage_deposit(account_id).unwrap_or_default());
+        self.adjust_total_issuance(held, 0);
+        for hashed_key in [
+            balance_of_key(account_id),
+            reserved_balance_of_key(account_id),
+            storage_deposit_of_key(account_id),
+            nonce_of_key(account_id),
+        ] {
+            self.hmap.remove(hashed_key.as_slice());
+        }
+        self.remove_contract(account_id)
+    }
+
+    /// Returns `true` if a contract with a code hash is stored at `account_id`.
+    pub fn contains_contract(&self, account_id: &[u8]) -> bool {
+        self.contract_accounts.contains(account_id)
+    }
+
+    /// Returns the accounts of all contracts which have a code hash, sorted by
+    /// account id.
+    pub fn deployed_contracts(&self) -> Vec<AccountId> {
+        self.contract_accounts
+            .iter()
+            .map(|account_id| AccountId::from_bytes(account_id))
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Database;
+    use super::{
+        AccountId,
+        Database,
+    };
 
     #[test]
     fn basic_operations() {
@@ -157,6 +580,26 @@ mod tests {
         assert_eq!(database.len(), 0);
     }
 
+    #[test]
+    fn contract_storage_len_does_not_mutate_storage() {
+        // given
+        let account_id = vec![1; 32];
+        let mut storage = Database::new();
+        storage.insert_into_contract_storage(&account_id, &[42], vec![7; 1024]);
+
+        // when
+        let len = storage.contract_storage_len(&account_id, &[42]);
+
+        // then
+        assert_eq!(len, Some(1024));
+        assert_eq!(storage.contract_storage_len(&account_id, &[43]), None);
+        assert_eq!(storage.len(), 1);
+        assert_eq!(
+            storage.get_from_contract_storage(&account_id, &[42]),
+            Some(&vec![7; 1024])
+        );
+    }
+
     #[test]
     fn contract_storage() {
         let account_id = vec![1; 32];
@@ -201,4 +644,199 @@ mod tests {
         );
         assert_eq!(storage.len(), 0);
     }
+
+    #[test]
+    fn update_contract_storage_mutates_in_place() {
+        // given
+        let account_id = vec![1; 32];
+        let mut database = Database::new();
+        database.insert_into_contract_storage(&account_id, &[42], vec![1, 2]);
+
+        // when
+        database.update_contract_storage(&account_id, &[42], |value| {
+            let value = value.expect("the value must exist");
+            value[0] += 1;
+            value.push(3);
+        });
+        let missing =
+            database.update_contract_storage(&account_id, &[43], |value| value.is_none());
+
+        // then
+        assert_eq!(
+            database.get_from_contract_storage(&account_id, &[42]),
+            Some(&vec![2, 2, 3])
+        );
+        assert!(missing);
+        assert_eq!(database.get_from_contract_storage(&account_id, &[43]), None);
+    }
+
+    #[test]
+    fn contract_storage_bytes_track_stored_values() {
+        // given
+        let alice = vec![1; 32];
+        let bob = vec![2; 32];
+        let mut database = Database::new();
+
+        // when
+        database.insert_into_contract_storage(&alice, &[1], vec![0; 10]);
+        database.insert_into_contract_storage(&alice, &[2], vec![0; 5]);
+        database.insert_into_contract_storage(&bob, &[1], vec![0; 3]);
+
+        // then
+        assert_eq!(database.contract_storage_bytes(&alice), 15);
+        assert_eq!(database.contract_storage_bytes(&bob), 3);
+        database.insert_into_contract_storage(&alice, &[1], vec![0; 4]);
+        assert_eq!(database.contract_storage_bytes(&alice), 9);
+        database.update_contract_storage(&alice, &[2], |value| {
+            value.expect("the value must exist").push(0)
+        });
+        assert_eq!(database.contract_storage_bytes(&alice), 10);
+        let snapshot = database.snapshot();
+        database.remove_contract_storage(&alice, &[1]);
+        assert_eq!(database.contract_storage_bytes(&alice), 6);
+        database.restore(snapshot);
+        assert_eq!(database.contract_storage_bytes(&alice), 10);
+        database.remove_contract(&alice);
+        assert_eq!(database.contract_storage_bytes(&alice), 0);
+        assert_eq!(database.contract_storage_bytes(&bob), 3);
+    }
+
+    #[test]
+    fn iter_contract_storage_is_sorted_by_key() {
+        let alice = vec![1; 32];
+        let bob = vec![2; 32];
+        let mut storage = Database::new();
+        storage.insert_into_contract_storage(&alice, &[43], vec![3]);
+        storage.insert_into_contract_storage(&alice, &[41], vec![1]);
+        storage.insert_into_contract_storage(&alice, &[42], vec![2]);
+        storage.insert_into_contract_storage(&bob, &[40], vec![0]);
+        storage.remove_contract_storage(&alice, &[42]);
+
+        let entries: Vec<_> = storage
+            .iter_contract_storage(&alice)
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        assert_eq!(entries, vec![(vec![41], vec![1]), (vec![43], vec![3])]);
+        storage.remove_contract_storage(&bob, &[40]);
+        assert_eq!(storage.iter_contract_storage(&bob).count(), 0);
+    }
+
+    #[test]
+    fn total_issuance_tracks_balances() {
+        let mut database = Database::new();
+        database.set_balance(&[1; 32], 10);
+        database.set_balance(&[2; 32], 20);
+        database.set_reserved_balance(&[2; 32], 5);
+        assert_eq!(database.total_issuance(), 35);
+
+        database.set_balance(&[1; 32], 4);
+        database.mint(&[3; 32], 7);
+        assert_eq!(database.burn(&[2; 32], 21), None);
+        assert_eq!(database.burn(&[2; 32], 10), Some(10));
+        assert_eq!(database.total_issuance(), 26);
+    }
+
+    #[test]
+    fn restore_reverts_to_snapshot() {
+        let account_id = vec![1; 32];
+        let mut database = Database::new();
+        database.set_balance(&account_id, 10);
+        let snapshot = database.snapshot();
+
+        database.insert_into_contract_storage(&account_id, &[42], vec![1]);
+        database.set_balance(&account_id, 20);
+        database.restore(snapshot);
+
+        assert_eq!(database.get_from_contract_storage(&account_id, &[42]), None);
+        assert_eq!(database.iter_contract_storage(&account_id).count(), 0);
+        assert_eq!(database.get_balance(&account_id), Some(10));
+        assert_eq!(database.total_issuance(), 10);
+    }
+
+    #[test]
+    fn state_digest_tracks_writes_and_restores() {
+        // given
+        let account_id = vec![1; 32];
+        let mut database = Database::new();
+        database.set_balance(&account_id, 10);
+        let initial = database.state_digest();
+        let snapshot = database.snapshot();
+
+        // when
+        database.insert_into_contract_storage(&account_id, &[42], vec![1]);
+        let written = database.state_digest();
+        database.restore(snapshot);
+
+        // then
+        assert_ne!(written, initial);
+        assert_eq!(database.state_digest(), initial);
+        database.set_balance(&account_id, 11);
+        assert_ne!(database.state_digest(), initial);
+    }
+
+    #[test]
+    fn state_digest_does_not_depend_on_the_order_of_writes() {
+        let mut first = Database::new();
+        first.set_balance(&[1; 32], 10);
+        first.insert_into_contract_storage(&[1; 32], &[42], vec![1]);
+        let mut second = Database::new();
+        second.insert_into_contract_storage(&[1; 32], &[42], vec![1]);
+        second.set_balance(&[1; 32], 10);
+
+        assert_eq!(first.state_digest(), second.state_digest());
+    }
+
+    #[test]
+    fn clear_account_removes_everything_of_the_account() {
+        // given
+        let mut database = Database::new();
+        let account_id = vec![1; 32];
+        let other = vec![2; 32];
+        database.set_balance(&account_id, 100);
+        database.set_reserved_balance(&account_id, 20);
+        database.set_storage_deposit(&account_id, 3);
+        database.increment_nonce(&account_id);
+        database.set_code_hash(&account_id, &[7; 32]);
+        database.insert_into_contract_storage(&account_id, &[42], vec![5]);
+        database.set_balance(&other, 50);
+
+        // when
+        let removed = database.clear_account(&account_id);
+
+        // then
+        assert_eq!(removed, vec![(vec![42], vec![5])]);
+        assert_eq!(database.get_balance(&account_id), None);
+        assert_eq!(database.get_reserved_balance(&account_id), None);
+        assert_eq!(database.get_storage_deposit(&account_id), None);
+        assert_eq!(database.nonce(&account_id), 0);
+        assert_eq!(database.get_code_hash(&account_id), None);
+        assert!(!database.contains_contract(&account_id));
+        assert_eq!(database.get_from_contract_storage(&account_id, &[42]), None);
+        assert_eq!(database.get_balance(&other), Some(50));
+        assert_eq!(database.total_issuance(), 50);
+        assert_eq!(database.len(), 1);
+    }
+
+    #[test]
+    fn deployed_contracts_are_listed() {
+        let alice = vec![1; 32];
+        let bob = vec![2; 32];
+        let mut database = Database::new();
+        database.set_balance(&[3; 32], 10);
+        let snapshot = database.snapshot();
+
+        database.set_code_hash(&bob, &[0x22; 32]);
+        database.set_code_hash(&alice, &[0x11; 32]);
+
+        assert!(database.contains_contract(&alice));
+        assert!(database.contains_contract(&bob));
+        assert!(!database.contains_contract(&[3; 32]));
+        assert_eq!(
+            database.deployed_contracts(),
+            vec![AccountId::from_bytes(&alice), AccountId::from_bytes(&bob)]
+        );
+        database.restore(snapshot);
+        assert!(database.deployed_contracts().is_empty());
+    }
 }
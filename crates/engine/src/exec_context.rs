@@ -21,7 +21,7 @@ use super::types::{
 
 /// The context of a contract execution.
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct ExecContext {
     /// The caller of the contract execution. Might be user or another contract.
     ///
@@ -37,6 +37,13 @@ pub struct ExecContext {
     /// by the crate which uses the `engine`. Methods which require a callee might
     /// panic when it has not been set.
     pub callee: Option<AccountId>,
+    /// The origin of the transaction which led to the contract execution.
+    ///
+    /// If it has not been set, the caller of the outermost contract execution is
+    /// considered to be the origin.
+    pub origin: Option<AccountId>,
+    /// Whether the origin of the transaction is root.
+    pub is_root: bool,
     /// The value transferred to the contract as part of the call.
     pub value_transferred: Balance,
     /// The current block number.
@@ -45,6 +52,20 @@ pub struct ExecContext {
     pub block_timestamp: BlockTimestamp,
     /// Known contract accounts
     pub contracts: Vec<Vec<u8>>,
+    /// The input passed to the contract execution.
+    pub input: Vec<u8>,
+    /// The output returned by the contract execution.
+    pub output: Vec<u8>,
+    /// The flags the contract execution returned with, using the same bit layout
+    /// as the `ReturnFlags` of the `contracts` pallet.
+    pub return_flags: u32,
+    /// The amount of gas consumed by the contract execution so far.
+    pub gas_consumed: u64,
+    /// The amount of consumed gas at which the contract execution runs out of gas.
+    ///
+    /// If it has not been set, the execution may consume gas up to the gas limit
+    /// of the chain.
+    pub gas_ceiling: Option<u64>,
 }
 
 impl ExecContext {
@@ -78,6 +99,27 @@ impl ExecContext {
     }
 }
 
+/// A frame of the call stack of the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// The caller of the contract execution.
+    pub caller: Option<AccountId>,
+    /// The callee of the contract execution.
+    pub callee: Option<AccountId>,
+    /// The value transferred to the callee as part of the call.
+    pub value_transferred: Balance,
+}
+
+impl From<&ExecContext> for Frame {
+    fn from(exec_context: &ExecContext) -> Self {
+        Self {
+            caller: exec_context.caller.clone(),
+            callee: exec_context.callee.clone(),
+            value_transferred: exec_context.value_transferred,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
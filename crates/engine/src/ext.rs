@@ -18,9 +18,15 @@
 //! for more information.
 
 use crate::{
+    address_deriver::{
+        AddressDeriver,
+        Blake2AddressDeriver,
+    },
     chain_extension::ChainExtensionHandler,
+    contracts::ContractStorage,
     database::Database,
     exec_context::ExecContext,
+    fault_injection::FaultInjector,
     test_api::{
         DebugInfo,
         EmittedEvent,
@@ -29,13 +35,86 @@ use crate::{
         AccountId,
         Balance,
         BlockTimestamp,
+        Weight,
     },
 };
 use scale::Encode;
-use std::panic::panic_any;
+use std::{
+    any::Any,
+    collections::HashMap,
+    panic::{
+        catch_unwind,
+        panic_any,
+        AssertUnwindSafe,
+    },
+};
 
 type Result = core::result::Result<(), Error>;
 
+/// Forwards the input of the caller to the callee and leaves the caller without input.
+const FORWARD_INPUT: u32 = 0b0001;
+/// Hands the callee a copy of the input of the caller.
+const CLONE_INPUT: u32 = 0b0010;
+/// Makes the output of the callee the output of the caller.
+const TAIL_CALL: u32 = 0b0100;
+/// Allows the callee to re-enter the caller.
+const ALLOW_REENTRY: u32 = 0b1000;
+
+/// The flags adjusting the behavior of a call, see [`Engine::call`].
+///
+/// Uses the same bit layout as the `CallFlags` of the `contracts` pallet.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CallFlags {
+    bits: u32,
+}
+
+impl CallFlags {
+    /// Returns the flags of a plain call.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the flags with the given bit layout.
+    pub fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Forwards the input of the caller to the callee and leaves the caller
+    /// without input.
+    pub fn forward_input(self) -> Self {
+        Self::from_bits(self.bits | FORWARD_INPUT)
+    }
+
+    /// Hands the callee a copy of the input of the caller.
+    pub fn clone_input(self) -> Self {
+        Self::from_bits(self.bits | CLONE_INPUT)
+    }
+
+    /// Makes the output of the callee the output of the caller.
+    pub fn tail_call(self) -> Self {
+        Self::from_bits(self.bits | TAIL_CALL)
+    }
+
+    /// Allows the callee to re-enter the caller.
+    pub fn allow_reentry(self) -> Self {
+        Self::from_bits(self.bits | ALLOW_REENTRY)
+    }
+
+    /// Returns the bit layout of the flags.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Returns `true` if `flag` is set.
+    fn contains(&self, flag: u32) -> bool {
+        self.bits & flag != 0
+    }
+}
+
+/// The return flag which signals that the changes of the contract execution
+/// are to be reverted.
+const REVERT: u32 = 0b0001;
+
 macro_rules! define_error_codes {
     (
         $(
@@ -95,10 +174,21 @@ define_error_codes! {
     /// The call to `debug_message` had no effect because debug message
     /// recording was disabled.
     LoggingDisabled = 9,
+    /// The call dispatched by `call_runtime` was executed but returned an error.
+    CallRuntimeFailed = 10,
     /// ECDSA public key recovery failed. Most probably wrong recovery id or signature.
     EcdsaRecoveryFailed = 11,
     /// sr25519 signature verification failed. This may be because of an invalid public key, invalid message or invalid signature.
     Sr25519VerifyFailed = 12,
+    /// A contract already exists at the address derived for the instantiation.
+    DuplicateContract = 13,
+    /// ed25519 signature verification failed. This may be because of an invalid
+    /// public key, invalid message or invalid signature.
+    Ed25519VerifyFailed = 14,
+    /// The output buffer supplied to the host function is too small for the value.
+    BufferTooSmall = 15,
+    /// The write would exceed the maximum number of bytes a contract may store.
+    StorageExhausted = 16,
 }
 
 /// The raw return code returned by the host side.
@@ -112,11 +202,58 @@ impl ReturnCode {
     }
 }
 
+/// A simulated runtime dispatchable.
+///
+/// It is invoked with the SCALE encoded arguments of the call, i.e. the call
+/// without its pallet and call index.
+pub type RuntimeCall = fn(&mut Engine, &[u8]) -> core::result::Result<(), Error>;
+
+/// The outcome of a contract termination.
+///
+/// `terminate` never returns, instead it panics with the SCALE encoded outcome as
+/// payload. This enables tests to check whether the termination succeeded.
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+pub enum TerminationResult<B> {
+    /// The remaining balance `value` was transferred to `beneficiary`.
+    Terminated { value: B, beneficiary: Vec<u8> },
+    /// The transfer of the remaining balance `value` to `beneficiary` was
+    /// rejected, e.g. because it would not satisfy the existential deposit.
+    TransferFailed { value: B, beneficiary: Vec<u8> },
+}
+
+/// The outcome of a contract execution which returned, see
+/// [`Engine::execute_entry_point`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecReturn {
+    /// The flags the contract returned with, see [`Engine::set_return`].
+    pub flags: u32,
+    /// The output the contract returned.
+    pub data: Vec<u8>,
+}
+
+impl ExecReturn {
+    /// Returns `true` if the contract reverted its execution.
+    pub fn did_revert(&self) -> bool {
+        self.flags & REVERT != 0
+    }
+}
+
+/// The reason of a contract execution trapping, see
+/// [`Engine::execute_entry_point`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrapReason {
+    /// The contract panicked with the given message.
+    Panic(String),
+    /// The contract tried to terminate itself, but the transfer of its remaining
+    /// balance failed.
+    TerminationFailed,
+}
+
 /// The off-chain engine.
 pub struct Engine {
     /// The environment database.
     pub database: Database,
-    /// The current execution context.
+    /// The current execution context, the top of the stack of execution contexts.
     pub exec_context: ExecContext,
     /// Recorder for relevant interactions with the engine.
     /// This is specifically about debug info. This info is
@@ -126,45 +263,214 @@ pub struct Engine {
     pub chain_spec: ChainSpec,
     /// Handler for registered chain extensions.
     pub chain_extension_handler: ChainExtensionHandler,
+    /// The contracts known to the engine.
+    pub contracts: ContractStorage,
+    /// The weights charged for calling host functions.
+    pub gas_model: GasModel,
+    /// Failures to be injected into the next host function calls.
+    pub fault_injector: FaultInjector,
+    /// The runtime dispatchables, keyed by their pallet and call index.
+    pub(crate) runtime_calls: HashMap<(u8, u8), RuntimeCall>,
+    /// The contexts of the contract executions suspended by a nested call, the
+    /// outermost context first.
+    pub(crate) suspended_contexts: Vec<ExecContext>,
+    /// Derives the addresses of instantiated contracts.
+    pub address_deriver: Box<dyn AddressDeriver>,
 }
 
 /// The chain specification.
 pub struct ChainSpec {
     /// The current gas price.
     pub gas_price: Balance,
+    /// The current price per byte of proof size.
+    pub storage_price: Balance,
     /// The minimum value an account of the chain must have
     /// (i.e. the chain's existential deposit).
+    ///
+    /// A minimum balance of `0` disables the existential deposit.
     pub minimum_balance: Balance,
     /// The targeted block time.
+    ///
+    /// A block time of `0` is allowed, all blocks then share the same timestamp.
     pub block_time: BlockTimestamp,
+    /// The amount of gas available to a contract execution.
+    pub gas_limit: u64,
+    /// The maximum number of contract frames which may be active at once.
+    pub max_call_depth: u32,
+    /// The seed from which the output of `random` is derived.
+    pub random_seed: [u8; 32],
+    /// The maximum number of topics an event may have.
+    pub max_event_topics: u32,
+    /// The deposit charged from the caller per byte a contract stores.
+    pub storage_deposit_per_byte: Balance,
+    /// The length of account addresses in bytes, e.g. 20 for chains using H160
+    /// addresses. At most 32.
+    pub address_len: usize,
+    /// The maximum total length in bytes of the recorded debug messages. A length
+    /// of `0` disables debug messages.
+    pub max_debug_buffer_len: usize,
+    /// Whether contracts may log debug messages.
+    pub debug_logging_enabled: bool,
+    /// The hash function with which the runtime hashes event topics.
+    pub topic_hasher: TopicHasher,
+    /// The maximum total length in bytes of the values a contract may store.
+    pub max_storage_bytes: u64,
 }
 
 /// The default values for the chain specification are:
 ///
 ///   * `gas_price`: 100
-///   * `minimum_balance`: 42
+///   * `storage_price`: 10
+///   * `minimum_balance`: 1000000
 ///   * `block_time`: 6
+///   * `gas_limit`: `u64::MAX`
+///   * `max_call_depth`: 32
+///   * `random_seed`: `[0; 32]`
+///   * `max_event_topics`: 4
+///   * `storage_deposit_per_byte`: 0
+///   * `address_len`: 32
+///   * `max_debug_buffer_len`: 2 MiB
+///   * `debug_logging_enabled`: `true`
+///   * `topic_hasher`: `TopicHasher::Blake2x256`
+///   * `max_storage_bytes`: `u64::MAX`
 ///
 /// There is no particular reason behind choosing them this way.
 impl Default for ChainSpec {
     fn default() -> Self {
         Self {
             gas_price: 100,
+            storage_price: 10,
             minimum_balance: 1000000,
             block_time: 6,
+            gas_limit: u64::MAX,
+            max_call_depth: 32,
+            random_seed: [0; 32],
+            max_event_topics: 4,
+            storage_deposit_per_byte: 0,
+            address_len: 32,
+            max_debug_buffer_len: 2 * 1024 * 1024,
+            debug_logging_enabled: true,
+            topic_hasher: TopicHasher::Blake2x256,
+            max_storage_bytes: u64::MAX,
         }
     }
 }
 
+/// The hash functions with which a runtime may hash event topics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TopicHasher {
+    /// BLAKE2 256-bit, as used by the `contracts` pallet.
+    #[default]
+    Blake2x256,
+    /// KECCAK 256-bit, as used by Ethereum compatible runtimes.
+    Keccak256,
+}
+
+impl TopicHasher {
+    /// Returns the length of a topic in bytes, i.e. the length of a hash.
+    pub fn topic_len(&self) -> usize {
+        match self {
+            Self::Blake2x256 | Self::Keccak256 => 32,
+        }
+    }
+
+    /// Hashes `input` and places the result into `output`.
+    pub fn hash(&self, input: &[u8], output: &mut [u8; 32]) {
+        match self {
+            Self::Blake2x256 => crate::hashing::blake2b_256(input, output),
+            Self::Keccak256 => crate::hashing::keccak_256(input, output),
+        }
+    }
+
+    /// Returns the topic under which an event field of `value` is recorded.
+    ///
+    /// Mirrors the topic hashing of ink! events: the SCALE encoding of `value`
+    /// is used as is, padded with zeros to 32 bytes, if it fits into 32 bytes.
+    /// Longer encodings are hashed.
+    pub fn topic_for<T: Encode>(&self, value: &T) -> Vec<u8> {
+        let encoded = value.encode();
+        let mut topic = vec![0; self.topic_len()];
+        if encoded.len() <= topic.len() {
+            topic[..encoded.len()].copy_from_slice(&encoded[..]);
+        } else {
+            let mut output = [0; 32];
+            self.hash(&encoded[..], &mut output);
+            topic.copy_from_slice(&output[..]);
+        }
+        topic
+    }
+}
+
+/// The weights charged for calling the host functions of the engine.
+///
+/// Host functions without an explicitly configured weight are charged the
+/// default weight.
+pub struct GasModel {
+    /// The weight charged for host functions without a configured weight.
+    default_weight: u64,
+    /// The weights of individual host functions, keyed by their name.
+    weights: HashMap<&'static str, u64>,
+}
+
+impl GasModel {
+    /// Creates a new gas model charging `default_weight` for every host function.
+    pub fn new(default_weight: u64) -> Self {
+        Self {
+            default_weight,
+            weights: HashMap::new(),
+        }
+    }
+
+    /// Sets the weight charged for calling the host function named `host_fn`.
+    pub fn with_weight(mut self, host_fn: &'static str, weight: u64) -> Self {
+        self.weights.insert(host_fn, weight);
+        self
+    }
+
+    /// Returns the weight charged for calling the host function named `host_fn`.
+    pub fn weight_of(&self, host_fn: &str) -> u64 {
+        self.weights
+            .get(host_fn)
+            .copied()
+            .unwrap_or(self.default_weight)
+    }
+}
+
+/// The default gas model charges a weight of 1 for every host function.
+impl Default for GasModel {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
 impl Engine {
     // Creates a new `Engine instance.
     pub fn new() -> Self {
+        Self::new_with_gas_model(GasModel::default())
+    }
+
+    /// Creates a new `Engine` instance with the given chain specification.
+    pub fn with_chain_spec(chain_spec: ChainSpec) -> Self {
+        Self {
+            chain_spec,
+            ..Self::new()
+        }
+    }
+
+    /// Creates a new `Engine` instance charging gas according to `gas_model`.
+    pub fn new_with_gas_model(gas_model: GasModel) -> Self {
         Self {
             database: Database::new(),
             exec_context: ExecContext::new(),
             debug_info: DebugInfo::new(),
             chain_spec: ChainSpec::default(),
             chain_extension_handler: ChainExtensionHandler::new(),
+            contracts: ContractStorage::new(),
+            gas_model,
+            fault_injector: FaultInjector::new(),
+            runtime_calls: HashMap::new(),
+            suspended_contexts: Vec::new(),
+            address_deriver: Box::new(Blake2AddressDeriver),
         }
     }
 }
@@ -177,7 +483,14 @@ impl Default for Engine {
 
 impl Engine {
     /// Transfers value from the contract to the destination account.
+    ///
+    /// Fails with `TransferFailed` if the balance of the contract is insufficient
+    /// or if the balance of the destination would overflow.
     pub fn transfer(&mut self, account_id: &[u8], mut value: &[u8]) -> Result {
+        self.charge_gas("transfer");
+        if self.fault_injector.take_transfer_failure() {
+            return Err(Error::TransferFailed)
+        }
         // Note that a transfer of `0` is allowed here
         let increment = <u128 as scale::Decode>::decode(&mut value)
             .map_err(|_| Error::TransferFailed)?;
@@ -187,39 +500,149 @@ impl Engine {
         let dest_old_balance = self.get_balance(dest.clone()).unwrap_or_default();
 
         let contract = self.get_callee();
+        // Only the free balance can be spent, reserved funds are kept separately
         let contract_old_balance = self
             .get_balance(contract.clone())
             .map_err(|_| Error::TransferFailed)?;
+        let contract_new_balance = contract_old_balance
+            .checked_sub(increment)
+            .ok_or(Error::TransferFailed)?;
+        let dest_new_balance = dest_old_balance
+            .checked_add(increment)
+            .ok_or(Error::TransferFailed)?;
 
-        self.database
-            .set_balance(&contract, contract_old_balance - increment);
-        self.database
-            .set_balance(&dest, dest_old_balance + increment);
+        // Neither account may be left with a balance below the existential deposit
+        let minimum_balance = self.chain_spec.minimum_balance;
+        if increment > 0 {
+            if contract_new_balance != 0 && contract_new_balance < minimum_balance {
+                return Err(Error::TransferFailed)
+            }
+            if dest_old_balance == 0 && increment < minimum_balance {
+                return Err(Error::TransferFailed)
+            }
+        }
+
+        self.database.set_balance(&contract, contract_new_balance);
+        self.database.set_balance(&dest, dest_new_balance);
+        Ok(())
+    }
+
+    /// Moves `value` from the free balance of `account` to its reserved balance.
+    ///
+    /// Fails with `TransferFailed` if the free balance is insufficient, if it would
+    /// be left below the existential deposit like by `transfer` or if the reserved
+    /// balance would overflow.
+    pub fn reserve(&mut self, account: &[u8], mut value: &[u8]) -> Result {
+        self.charge_gas("reserve");
+        let value = <u128 as scale::Decode>::decode(&mut value)
+            .map_err(|_| Error::TransferFailed)?;
+
+        let free = self.database.get_balance(account).unwrap_or_default();
+        let reserved = self
+            .database
+            .get_reserved_balance(account)
+            .unwrap_or_default();
+        let new_free = free.checked_sub(value).ok_or(Error::TransferFailed)?;
+        let new_reserved = reserved.checked_add(value).ok_or(Error::TransferFailed)?;
+        if new_free != 0 && new_free < self.chain_spec.minimum_balance {
+            return Err(Error::TransferFailed)
+        }
+
+        self.database.set_balance(account, new_free);
+        self.database.set_reserved_balance(account, new_reserved);
+        Ok(())
+    }
+
+    /// Moves `value` from the reserved balance of `account` back to its free balance.
+    ///
+    /// Fails with `TransferFailed` if the reserved balance is insufficient or if
+    /// the free balance would overflow.
+    pub fn unreserve(&mut self, account: &[u8], mut value: &[u8]) -> Result {
+        self.charge_gas("unreserve");
+        let value = <u128 as scale::Decode>::decode(&mut value)
+            .map_err(|_| Error::TransferFailed)?;
+
+        let free = self.database.get_balance(account).unwrap_or_default();
+        let reserved = self
+            .database
+            .get_reserved_balance(account)
+            .unwrap_or_default();
+        let new_reserved = reserved.checked_sub(value).ok_or(Error::TransferFailed)?;
+        let new_free = free.checked_add(value).ok_or(Error::TransferFailed)?;
+
+        self.database.set_reserved_balance(account, new_reserved);
+        self.database.set_balance(account, new_free);
         Ok(())
     }
 
+    /// Creates `amount` of new balance in the free balance of `account`.
+    pub fn mint(&mut self, account: &[u8], amount: Balance) {
+        self.database.mint(account, amount);
+    }
+
+    /// Destroys `amount` of the free balance of `account`.
+    ///
+    /// Fails with `TransferFailed` if the free balance is insufficient.
+    pub fn burn(&mut self, account: &[u8], amount: Balance) -> Result {
+        self.database
+            .burn(account, amount)
+            .map(|_| ())
+            .ok_or(Error::TransferFailed)
+    }
+
     /// Deposits an event identified by the supplied topics and data.
     pub fn deposit_event(&mut self, topics: &[u8], data: &[u8]) {
+        self.charge_gas("deposit_event");
         // The first byte contains the number of topics in the slice
+        assert!(
+            !topics.is_empty(),
+            "the topics buffer is empty, but it must start with the number of topics"
+        );
         let topics_count: scale::Compact<u32> = scale::Decode::decode(&mut &topics[0..1])
             .unwrap_or_else(|err| panic!("decoding number of topics failed: {err}"));
+        let max_event_topics = self.chain_spec.max_event_topics;
+        assert!(
+            topics_count.0 <= max_event_topics,
+            "the event has {} topics, but at most {max_event_topics} are allowed",
+            topics_count.0,
+        );
         let topics_count = topics_count.0 as usize;
 
-        let topics_vec = if topics_count > 0 {
-            // The rest of the slice contains the topics
-            let topics = &topics[1..];
-            let bytes_per_topic = topics.len() / topics_count;
-            let topics_vec: Vec<Vec<u8>> = topics
-                .chunks(bytes_per_topic)
-                .map(|chunk| chunk.to_vec())
-                .collect();
-            assert_eq!(topics_count, topics_vec.len());
-            topics_vec
-        } else {
-            Vec::new()
+        // The rest of the slice contains the topics
+        let topics = &topics[1..];
+        let topics_vec = match topics.len().checked_div(topics_count) {
+            Some(bytes_per_topic) => {
+                assert!(
+                    bytes_per_topic * topics_count == topics.len(),
+                    "the topics buffer of {} bytes cannot be split into {topics_count} \
+                    topics of equal size",
+                    topics.len(),
+                );
+                let topic_len = self.chain_spec.topic_hasher.topic_len();
+                assert_eq!(
+                    bytes_per_topic, topic_len,
+                    "every topic must be {topic_len} bytes, but the topics are \
+                    {bytes_per_topic} bytes"
+                );
+                topics
+                    .chunks(bytes_per_topic)
+                    .map(|chunk| chunk.to_vec())
+                    .collect()
+            }
+            None => {
+                assert!(
+                    topics.is_empty(),
+                    "the event has no topics, but the topics buffer contains {} \
+                    trailing bytes",
+                    topics.len(),
+                );
+                Vec::new()
+            }
         };
 
         self.debug_info.record_event(EmittedEvent {
+            index: self.debug_info.events_recorded(),
+            block_number: self.exec_context.block_number,
             topics: topics_vec,
             data: data.to_vec(),
         });
@@ -227,110 +650,257 @@ impl Engine {
 
     /// Writes the encoded value into the storage at the given key.
     /// Returns the size of the previously stored value at the key if any.
-    pub fn set_storage(&mut self, key: &[u8], encoded_value: &[u8]) -> Option<u32> {
+    ///
+    /// Fails with `StorageExhausted` if the contract would store more than
+    /// `max_storage_bytes` in total afterwards, and with `TransferFailed` if the
+    /// caller cannot pay the storage deposit for the additionally stored bytes.
+    pub fn set_storage(
+        &mut self,
+        key: &[u8],
+        encoded_value: &[u8],
+    ) -> core::result::Result<Option<u32>, Error> {
+        self.charge_gas("set_storage");
         let callee = self.get_callee();
         let account_id = AccountId::from_bytes(&callee[..]);
+        let old_len = self
+            .database
+            .get_from_contract_storage(&callee, key)
+            .map_or(0, Vec::len);
+        let stored_bytes = self.database.contract_storage_bytes(&callee) - old_len as u64
+            + encoded_value.len() as u64;
+        if stored_bytes > self.chain_spec.max_storage_bytes {
+            return Err(Error::StorageExhausted)
+        }
+        self.settle_storage_deposit(&callee, old_len, encoded_value.len())?;
 
-        self.debug_info.inc_writes(account_id.clone());
+        self.debug_info.inc_writes(account_id.clone(), key);
         self.debug_info
             .record_cell_for_account(account_id, key.to_vec());
 
-        self.database
+        Ok(self
+            .database
             .insert_into_contract_storage(&callee, key, encoded_value.to_vec())
-            .map(|v| <u32>::try_from(v.len()).expect("usize to u32 conversion failed"))
+            .map(|v| <u32>::try_from(v.len()).expect("usize to u32 conversion failed")))
+    }
+
+    /// Writes the encoded value into the storage at the given key, like
+    /// [`Engine::set_storage`], but returns by how many bytes the value at the
+    /// key grew, i.e. a negative number if it shrank.
+    ///
+    /// A key without a previously stored value counts as a value of length `0`.
+    pub fn set_storage_returning_delta(
+        &mut self,
+        key: &[u8],
+        encoded_value: &[u8],
+    ) -> core::result::Result<i64, Error> {
+        let old_len = self.set_storage(key, encoded_value)?.unwrap_or(0);
+        Ok(encoded_value.len() as i64 - i64::from(old_len))
     }
 
     /// Returns the decoded contract storage at the key if any.
     pub fn get_storage(&mut self, key: &[u8], output: &mut &mut [u8]) -> Result {
+        self.charge_gas("get_storage");
         let callee = self.get_callee();
         let account_id = AccountId::from_bytes(&callee[..]);
 
-        self.debug_info.inc_reads(account_id);
+        self.debug_info.inc_reads(account_id, key);
         match self.database.get_from_contract_storage(&callee, key) {
-            Some(val) => {
-                set_output(output, val);
-                Ok(())
-            }
+            Some(val) => try_set_output(output, val),
             None => Err(Error::KeyNotFound),
         }
     }
 
+    /// Copies the contract storage at the key starting at `offset` into `output`.
+    ///
+    /// Copies at most `output.len()` bytes and nothing if `offset` is beyond the
+    /// end of the value. Returns the total length of the value.
+    ///
+    /// # Errors
+    ///
+    /// If no value is stored at the key.
+    pub fn get_storage_partial(
+        &mut self,
+        key: &[u8],
+        offset: u32,
+        output: &mut &mut [u8],
+    ) -> core::result::Result<u32, Error> {
+        self.charge_gas("get_storage_partial");
+        let callee = self.get_callee();
+        let account_id = AccountId::from_bytes(&callee[..]);
+
+        self.debug_info.inc_reads(account_id, key);
+        let value = self
+            .database
+            .get_from_contract_storage(&callee, key)
+            .ok_or(Error::KeyNotFound)?;
+        let start = core::cmp::min(offset as usize, value.len());
+        let len = core::cmp::min(value.len() - start, output.len());
+        output[..len].copy_from_slice(&value[start..start + len]);
+        Ok(value.len() as u32)
+    }
+
     /// Removes the storage entries at the given key,
     /// returning previously stored value at the key if any.
+    ///
+    /// Fails with `BufferTooSmall` and keeps the value in storage if it does not
+    /// fit into `output`.
     pub fn take_storage(&mut self, key: &[u8], output: &mut &mut [u8]) -> Result {
+        self.charge_gas("take_storage");
         let callee = self.get_callee();
         let account_id = AccountId::from_bytes(&callee[..]);
 
-        self.debug_info.inc_writes(account_id);
-        match self.database.remove_contract_storage(&callee, key) {
-            Some(val) => {
-                set_output(output, &val);
-                Ok(())
-            }
-            None => Err(Error::KeyNotFound),
+        match self.database.get_from_contract_storage(&callee, key) {
+            Some(val) if val.len() > output.len() => return Err(Error::BufferTooSmall),
+            Some(_) => (),
+            None => return Err(Error::KeyNotFound),
         }
+        self.debug_info.inc_writes(account_id, key);
+        let val = self
+            .database
+            .remove_contract_storage(&callee, key)
+            .expect("the value must exist since it was just read");
+        self.refund_storage_deposit(&callee, val.len());
+        set_output(output, &val);
+        Ok(())
+    }
+
+    /// Removes the storage entries at the given key,
+    /// returning previously stored value at the key if any.
+    ///
+    /// Fails with `BufferTooSmall` and keeps the value in storage if it does not
+    /// fit into `output`. This is what `take_storage` does as well.
+    pub fn take_storage_checked(&mut self, key: &[u8], output: &mut &mut [u8]) -> Result {
+        self.take_storage(key, output)
     }
 
     /// Returns the size of the value stored in the contract storage at the key if any.
     pub fn contains_storage(&mut self, key: &[u8]) -> Option<u32> {
+        self.charge_gas("contains_storage");
         let callee = self.get_callee();
         let account_id = AccountId::from_bytes(&callee[..]);
 
-        self.debug_info.inc_reads(account_id);
-        self.database
-            .get_from_contract_storage(&callee, key)
-            .map(|val| val.len() as u32)
+        self.debug_info.inc_reads(account_id, key);
+        self.database.contract_storage_len(&callee, key)
     }
 
     /// Removes the storage entries at the given key.
     /// Returns the size of the previously stored value at the key if any.
     pub fn clear_storage(&mut self, key: &[u8]) -> Option<u32> {
+        self.charge_gas("clear_storage");
         let callee = self.get_callee();
         let account_id = AccountId::from_bytes(&callee[..]);
-        self.debug_info.inc_writes(account_id.clone());
+        self.debug_info.inc_writes(account_id.clone(), key);
         let _ = self
             .debug_info
             .remove_cell_for_account(account_id, key.to_vec());
-        self.database
-            .remove_contract_storage(&callee, key)
-            .map(|val| val.len() as u32)
+        let removed = self.database.remove_contract_storage(&callee, key);
+        if let Some(val) = &removed {
+            self.refund_storage_deposit(&callee, val.len());
+        }
+        removed.map(|val| val.len() as u32)
+    }
+
+    /// Removes all storage entries whose key starts with `prefix`.
+    /// Returns the number of removed entries.
+    pub fn clear_storage_prefix(&mut self, prefix: &[u8]) -> u32 {
+        self.charge_gas("clear_storage_prefix");
+        let callee = self.get_callee();
+        let account_id = AccountId::from_bytes(&callee[..]);
+        let removed = self
+            .database
+            .remove_contract_storage_prefix(&callee, prefix);
+        for (key, value) in &removed {
+            self.debug_info.inc_writes(account_id.clone(), key);
+            let _ = self
+                .debug_info
+                .remove_cell_for_account(account_id.clone(), key.clone());
+            self.refund_storage_deposit(&callee, value.len());
+        }
+        removed.len() as u32
     }
 
     /// Remove the calling account and transfer remaining balance.
     ///
     /// This function never returns. Either the termination was successful and the
     /// execution of the destroyed contract is halted. Or it failed during the
-    /// termination which is considered fatal.
+    /// termination which is considered fatal. In both cases it panics with the
+    /// encoded [`TerminationResult`].
     pub fn terminate(&mut self, beneficiary: &[u8]) -> ! {
+        self.charge_gas("terminate");
         // Send the remaining balance to the beneficiary
         let contract = self.get_callee();
         let all = self
-            .get_balance(contract)
+            .get_balance(contract.clone())
             .unwrap_or_else(|err| panic!("could not get balance: {err:?}"));
         let value = &scale::Encode::encode(&all)[..];
-        self.transfer(beneficiary, value)
-            .unwrap_or_else(|err| panic!("transfer did not work: {err:?}"));
+        let beneficiary = beneficiary.to_vec();
+        let res = match self.transfer(&beneficiary, value) {
+            Ok(()) => {
+                self.remove_contract(&contract);
+                TerminationResult::Terminated {
+                    value: all,
+                    beneficiary,
+                }
+            }
+            Err(_) => {
+                TerminationResult::TransferFailed {
+                    value: all,
+                    beneficiary,
+                }
+            }
+        };
 
         // Encode the result of the termination and panic with it.
         // This enables testing for the proper result and makes sure this
         // method returns `Never`.
-        let res = (all, beneficiary.to_vec());
         panic_any(scale::Encode::encode(&res));
     }
 
+    /// Sets the output of the current contract execution to `data` and records the
+    /// return `flags`.
+    ///
+    /// Unlike `seal_return` of the `contracts` pallet this does not end the
+    /// execution. If `flags` has the revert bit set, `call` and `instantiate` fail
+    /// with `CalleeReverted` and revert the changes of the execution, but still
+    /// write `data` into their output.
+    pub fn set_return(&mut self, flags: u32, data: &[u8]) {
+        self.charge_gas("set_return");
+        self.exec_context.return_flags = flags;
+        self.exec_context.output = data.to_vec();
+    }
+
     /// Returns the address of the caller.
-    pub fn caller(&self, output: &mut &mut [u8]) {
+    pub fn caller(&mut self, output: &mut &mut [u8]) {
+        self.charge_gas("caller");
         let caller = self
             .exec_context
             .caller
             .as_ref()
             .expect("no caller has been set")
             .as_bytes();
+        self.assert_address_len("caller", caller);
         set_output(output, caller);
     }
 
+    /// Returns `true` if the caller is the origin of the transaction.
+    ///
+    /// This is never the case within a nested call, since the caller is a contract.
+    pub fn caller_is_origin(&self) -> bool {
+        match self.exec_context.origin {
+            Some(ref origin) => self.exec_context.caller.as_ref() == Some(origin),
+            None => true,
+        }
+    }
+
+    /// Returns `true` if the caller is the origin of the transaction and the
+    /// origin is root.
+    pub fn caller_is_root(&self) -> bool {
+        self.exec_context.is_root && self.caller_is_origin()
+    }
+
     /// Returns the balance of the executed contract.
-    pub fn balance(&self, output: &mut &mut [u8]) {
+    pub fn balance(&mut self, output: &mut &mut [u8]) {
+        self.charge_gas("balance");
         let contract = self
             .exec_context
             .callee
@@ -346,27 +916,53 @@ impl Engine {
     }
 
     /// Returns the transferred value for the called contract.
-    pub fn value_transferred(&self, output: &mut &mut [u8]) {
+    pub fn value_transferred(&mut self, output: &mut &mut [u8]) {
+        self.charge_gas("value_transferred");
         let value_transferred: Vec<u8> =
             scale::Encode::encode(&self.exec_context.value_transferred);
         set_output(output, &value_transferred[..])
     }
 
     /// Returns the address of the executed contract.
-    pub fn address(&self, output: &mut &mut [u8]) {
+    pub fn address(&mut self, output: &mut &mut [u8]) {
+        self.charge_gas("address");
         let callee = self
             .exec_context
             .callee
             .as_ref()
             .expect("no callee has been set")
             .as_bytes();
+        self.assert_address_len("callee", callee);
         set_output(output, callee)
     }
 
+    /// Writes the input of the executed contract into `output`.
+    pub fn input(&mut self, output: &mut &mut [u8]) {
+        self.charge_gas("input");
+        set_output(output, &self.exec_context.input[..])
+    }
+
     /// Records the given debug message and appends to stdout.
-    pub fn debug_message(&mut self, message: &str) {
+    ///
+    /// A message which does not fit into the remaining debug buffer is dropped,
+    /// which is recorded as truncation of the debug messages.
+    ///
+    /// Fails with `LoggingDisabled` without recording or printing the message if
+    /// debug logging is disabled or the chain has no debug buffer.
+    pub fn debug_message(&mut self, message: &str) -> Result {
+        self.charge_gas("debug_message");
+        let max_debug_buffer_len = self.chain_spec.max_debug_buffer_len;
+        if !self.chain_spec.debug_logging_enabled || max_debug_buffer_len == 0 {
+            return Err(Error::LoggingDisabled)
+        }
+        let debug_buffer_len = self.debug_info.debug_buffer_len();
+        if debug_buffer_len.saturating_add(message.len()) > max_debug_buffer_len {
+            self.debug_info.record_debug_truncation();
+            return Ok(())
+        }
         self.debug_info.record_debug_message(String::from(message));
         print!("{message}");
+        Ok(())
     }
 
     /// Conduct the BLAKE-2 256-bit hash and place the result into `output`.
@@ -374,6 +970,11 @@ impl Engine {
         super::hashing::blake2b_256(input, output);
     }
 
+    /// Conduct the BLAKE-2 512-bit hash and place the result into `output`.
+    pub fn hash_blake2_512(input: &[u8], output: &mut [u8; 64]) {
+        super::hashing::blake2b_512(input, output);
+    }
+
     /// Conduct the BLAKE-2 128-bit hash and place the result into `output`.
     pub fn hash_blake2_128(input: &[u8], output: &mut [u8; 16]) {
         super::hashing::blake2b_128(input, output);
@@ -384,88 +985,720 @@ impl Engine {
         super::hashing::sha2_256(input, output);
     }
 
+    /// Conduct the SHA-2 512-bit hash and place the result into `output`.
+    pub fn hash_sha2_512(input: &[u8], output: &mut [u8; 64]) {
+        super::hashing::sha2_512(input, output);
+    }
+
     /// Conduct the KECCAK 256-bit hash and place the result into `output`.
     pub fn hash_keccak_256(input: &[u8], output: &mut [u8; 32]) {
         super::hashing::keccak_256(input, output);
     }
 
+    /// Conduct the RIPEMD 160-bit hash and place the result into `output`.
+    pub fn hash_ripemd_160(input: &[u8], output: &mut [u8; 20]) {
+        super::hashing::ripemd160(input, output);
+    }
+
     /// Returns the current block number.
-    pub fn block_number(&self, output: &mut &mut [u8]) {
+    pub fn block_number(&mut self, output: &mut &mut [u8]) {
+        self.charge_gas("block_number");
         let block_number: Vec<u8> =
             scale::Encode::encode(&self.exec_context.block_number);
         set_output(output, &block_number[..])
     }
 
+    /// Writes pseudo-random bytes derived from `subject` into `output`.
+    ///
+    /// The bytes are the BLAKE2 256-bit hash of the configured random seed, the
+    /// `subject` and the current block number. They are therefore deterministic
+    /// and change with every block.
+    pub fn random(&mut self, subject: &[u8], output: &mut &mut [u8]) {
+        self.charge_gas("random");
+        let mut entropy = self.chain_spec.random_seed.to_vec();
+        entropy.extend_from_slice(subject);
+        entropy.extend(scale::Encode::encode(&self.exec_context.block_number));
+        let mut random = [0; 32];
+        super::hashing::blake2b_256(&entropy[..], &mut random);
+        set_output(output, &random[..])
+    }
+
     /// Returns the timestamp of the current block.
-    pub fn block_timestamp(&self, output: &mut &mut [u8]) {
+    pub fn block_timestamp(&mut self, output: &mut &mut [u8]) {
+        self.charge_gas("block_timestamp");
         let block_timestamp: Vec<u8> =
             scale::Encode::encode(&self.exec_context.block_timestamp);
         set_output(output, &block_timestamp[..])
     }
 
-    pub fn gas_left(&self, _output: &mut &mut [u8]) {
-        unimplemented!("off-chain environment does not yet support `gas_left`");
+    /// Returns the amount of gas left for the contract execution.
+    pub fn gas_left(&mut self, output: &mut &mut [u8]) {
+        self.charge_gas("gas_left");
+        let gas_left = self
+            .gas_ceiling()
+            .saturating_sub(self.exec_context.gas_consumed);
+        let gas_left: Vec<u8> = scale::Encode::encode(&gas_left);
+        set_output(output, &gas_left[..])
     }
 
     /// Returns the minimum balance that is required for creating an account
     /// (i.e. the chain's existential deposit).
-    pub fn minimum_balance(&self, output: &mut &mut [u8]) {
+    pub fn minimum_balance(&mut self, output: &mut &mut [u8]) {
+        self.charge_gas("minimum_balance");
         let minimum_balance: Vec<u8> =
             scale::Encode::encode(&self.chain_spec.minimum_balance);
         set_output(output, &minimum_balance[..])
     }
 
+    /// Replaces the code of the contract at `account` with the code registered
+    /// under `code_hash`.
+    ///
+    /// Fails with `CodeNotFound` if no code is registered under `code_hash`.
+    pub fn set_code_hash(&mut self, account: &[u8], code_hash: &[u8]) -> Result {
+        self.charge_gas("set_code_hash");
+        if self.contracts.get(code_hash).is_none() {
+            return Err(Error::CodeNotFound)
+        }
+        self.database.set_code_hash(account, code_hash);
+        Ok(())
+    }
+
+    /// Writes the code hash of the contract at `account` into `output`.
+    ///
+    /// Fails with `CodeNotFound` if `account` is not a contract.
+    pub fn code_hash(&mut self, account: &[u8], output: &mut &mut [u8]) -> Result {
+        self.charge_gas("code_hash");
+        let code_hash = self
+            .database
+            .get_code_hash(account)
+            .ok_or(Error::CodeNotFound)?;
+        set_output(output, &code_hash[..]);
+        Ok(())
+    }
+
+    /// Writes the code hash of the currently executing contract into `output`.
+    ///
+    /// Fails with `CodeNotFound` if the callee is not a contract.
+    pub fn own_code_hash(&mut self, output: &mut &mut [u8]) -> Result {
+        self.charge_gas("own_code_hash");
+        let callee = self
+            .exec_context
+            .callee
+            .as_ref()
+            .expect("no callee has been set");
+        let code_hash = self
+            .database
+            .get_code_hash(callee.as_bytes())
+            .ok_or(Error::CodeNotFound)?;
+        set_output(output, &code_hash[..]);
+        Ok(())
+    }
+
+    /// Returns `true` if `account` is a contract.
+    ///
+    /// This is the case if code is deployed at `account` or if it was marked as
+    /// a contract via `set_contract`.
+    pub fn is_contract(&self, account: &[u8]) -> bool {
+        self.contracts.deployed.contains_key(account)
+            || self.database.get_code_hash(account).is_some()
+            || self
+                .exec_context
+                .contracts
+                .iter()
+                .any(|c| &c[..] == account)
+    }
+
+    /// Instantiates a new contract from the code registered under `code_hash`.
+    ///
+    /// The address of the new contract is derived from the caller, `code_hash`,
+    /// `input` and `salt` and written into `out_address`. The output of the
+    /// constructor is written into `out_return_value`.
+    ///
+    /// The constructor may consume up to the `ref_time` of `gas_limit`, or all of
+    /// the gas left if it is `0`. A successful instantiation increments the nonce of the
+    /// caller.
+    ///
+    /// The constructor counts toward the maximum call depth, `CalleeTrapped` is
+    /// returned if it has been reached.
     #[allow(clippy::too_many_arguments)]
     pub fn instantiate(
         &mut self,
-        _code_hash: &[u8],
-        _gas_limit: u64,
-        _endowment: &[u8],
-        _input: &[u8],
-        _out_address: &mut &mut [u8],
-        _out_return_value: &mut &mut [u8],
-        _salt: &[u8],
+        code_hash: &[u8],
+        gas_limit: Weight,
+        endowment: &[u8],
+        input: &[u8],
+        out_address: &mut &mut [u8],
+        out_return_value: &mut &mut [u8],
+        salt: &[u8],
     ) -> Result {
-        unimplemented!("off-chain environment does not yet support `instantiate`");
+        self.charge_gas("instantiate");
+        let endowment = <Balance as scale::Decode>::decode(&mut &endowment[..])
+            .map_err(|_| Error::TransferFailed)?;
+        let contract = self.contracts.get(code_hash).ok_or(Error::CodeNotFound)?;
+
+        let caller = self.get_callee();
+        let mut address = self.address_deriver.derive(code_hash, input, salt, &caller);
+        address.truncate(self.chain_spec.address_len);
+        if self.is_contract(&address) {
+            return Err(Error::DuplicateContract)
+        }
+        if endowment > 0 {
+            let caller_balance = self.get_balance(caller.clone()).unwrap_or_default();
+            if endowment > caller_balance {
+                return Err(Error::TransferFailed)
+            }
+        }
+
+        self.enter_constructor(&caller, &address)?;
+
+        let snapshot = self.database.snapshot();
+        if endowment > 0 {
+            if let Err(err) = self.transfer(&address, &scale::Encode::encode(&endowment))
+            {
+                self.database.restore(snapshot);
+                self.leave_contract(&caller, &address);
+                return Err(err)
+            }
+        } else if self.database.get_balance(&address).is_none() {
+            self.database.set_balance(&address, 0);
+        }
+        self.database.set_code_hash(&address, code_hash);
+
+        let mut frame = self.nested_context(&address, endowment, input.to_vec());
+        frame.gas_ceiling = Some(self.nested_gas_ceiling(gas_limit.ref_time));
+        let result = self.execute(frame, contract.deploy);
+        self.leave_contract(&caller, &address);
+        let frame = match result {
+            Ok(frame) => frame,
+            Err(err) => {
+                self.database.restore(snapshot);
+                return Err(err)
+            }
+        };
+        if frame.return_flags & REVERT != 0 {
+            self.database.restore(snapshot);
+            set_output(out_return_value, &frame.output[..]);
+            return Err(Error::CalleeReverted)
+        }
+        self.database.release(snapshot);
+        self.database.increment_nonce(&caller);
+
+        set_output(out_address, &address[..]);
+        set_output(out_return_value, &frame.output[..]);
+        Ok(())
     }
 
+    /// Calls the contract at `callee` with `input` and the attached `value`.
+    ///
+    /// The output of the callee is written into `output`. The behavior of the call
+    /// can be adjusted via `flags`.
+    ///
+    /// The callee may consume up to the `ref_time` of `gas_limit`, or all of the gas
+    /// left if it is `0`. Running out of gas traps the callee, the gas the callee did not
+    /// consume is left to the caller.
+    ///
+    /// Returns the flags the callee returned with, see [`Engine::set_return`].
+    ///
+    /// # Errors
+    ///
+    /// - `CalleeReverted` if the callee returned with the revert flag set. Its output is
+    ///   still written into `output`.
+    /// - `CalleeTrapped` if the callee trapped. Its output is discarded.
     pub fn call(
         &mut self,
-        _callee: &[u8],
-        _gas_limit: u64,
-        _value: &[u8],
-        _input: &[u8],
-        _output: &mut &mut [u8],
+        flags: CallFlags,
+        callee: &[u8],
+        gas_limit: Weight,
+        value: &[u8],
+        input: &[u8],
+        output: &mut &mut [u8],
+    ) -> core::result::Result<u32, Error> {
+        self.charge_gas("call");
+        let value = <Balance as scale::Decode>::decode(&mut &value[..])
+            .map_err(|_| Error::TransferFailed)?;
+        let contract = self
+            .database
+            .get_code_hash(callee)
+            .and_then(|code_hash| self.contracts.get(&code_hash))
+            .ok_or(Error::NotCallable)?;
+
+        let caller = self.get_callee();
+        if value > 0 {
+            let caller_balance = self.get_balance(caller.clone()).unwrap_or_default();
+            if value > caller_balance {
+                return Err(Error::TransferFailed)
+            }
+        }
+
+        let input = self.apply_code_flags_before_call(&caller, callee, flags, input)?;
+        let snapshot = self.database.snapshot();
+        if value > 0 {
+            if let Err(err) = self.transfer(callee, &scale::Encode::encode(&value)) {
+                self.database.restore(snapshot);
+                self.apply_code_flags_after_call(&caller, callee, CallFlags::new(), &[]);
+                return Err(err)
+            }
+        }
+
+        let mut frame = self.nested_context(callee, value, input);
+        frame.gas_ceiling = Some(self.nested_gas_ceiling(gas_limit.ref_time));
+        let frame = match self.execute(frame, contract.call) {
+            Ok(frame) => frame,
+            Err(err) => {
+                self.database.restore(snapshot);
+                self.apply_code_flags_after_call(&caller, callee, CallFlags::new(), &[]);
+                return Err(err)
+            }
+        };
+        let reverted = frame.return_flags & REVERT != 0;
+        if reverted {
+            self.database.restore(snapshot);
+        } else {
+            self.database.release(snapshot);
+        }
+
+        self.apply_code_flags_after_call(&caller, callee, flags, &frame.output);
+        set_output(output, &frame.output[..]);
+        if reverted {
+            return Err(Error::CalleeReverted)
+        }
+        Ok(frame.return_flags)
+    }
+
+    /// Returns the execution context for `callee` being invoked by the currently
+    /// executing contract.
+    pub(crate) fn nested_context(
+        &self,
+        callee: &[u8],
+        value_transferred: Balance,
+        input: Vec<u8>,
+    ) -> ExecContext {
+        ExecContext {
+            caller: self.exec_context.callee.clone(),
+            callee: Some(AccountId::from_bytes(callee)),
+            origin: self
+                .exec_context
+                .origin
+                .clone()
+                .or_else(|| self.exec_context.caller.clone()),
+            is_root: self.exec_context.is_root,
+            value_transferred,
+            block_number: self.exec_context.block_number,
+            block_timestamp: self.exec_context.block_timestamp,
+            contracts: self.exec_context.contracts.clone(),
+            input,
+            output: Vec::new(),
+            return_flags: 0,
+            gas_consumed: self.exec_context.gas_consumed,
+            gas_ceiling: self.exec_context.gas_ceiling,
+        }
+    }
+
+    /// Returns the amount of consumed gas at which the currently executing
+    /// contract runs out of gas.
+    fn gas_ceiling(&self) -> u64 {
+        self.exec_context
+            .gas_ceiling
+            .unwrap_or(self.chain_spec.gas_limit)
+    }
+
+    /// Returns the gas ceiling of a nested contract execution which may consume
+    /// up to `gas_limit`.
+    ///
+    /// A `gas_limit` of `0` or beyond the gas left makes all of the gas left
+    /// available to the nested execution.
+    fn nested_gas_ceiling(&self, gas_limit: u64) -> u64 {
+        let gas_left = self
+            .gas_ceiling()
+            .saturating_sub(self.exec_context.gas_consumed);
+        let budget = if gas_limit == 0 {
+            gas_left
+        } else {
+            core::cmp::min(gas_limit, gas_left)
+        };
+        self.exec_context.gas_consumed.saturating_add(budget)
+    }
+
+    /// Runs `entry_point` within `frame` and restores the current execution
+    /// context afterwards.
+    ///
+    /// Returns the execution context of the finished `entry_point`, or
+    /// `CalleeTrapped` if it panicked for another reason than a successful
+    /// termination.
+    fn execute(
+        &mut self,
+        frame: ExecContext,
+        entry_point: fn(&mut Engine),
+    ) -> core::result::Result<ExecContext, Error> {
+        self.push_context(frame);
+        let result = catch_unwind(AssertUnwindSafe(|| entry_point(self)));
+        let frame = self
+            .pop_context()
+            .expect("the context of the execution must have been pushed");
+        self.exec_context.gas_consumed = frame.gas_consumed;
+        match result {
+            Ok(()) => Ok(frame),
+            // A contract which terminated itself has finished successfully.
+            Err(payload) if is_successful_termination(&*payload) => Ok(frame),
+            Err(_) => Err(Error::CalleeTrapped),
+        }
+    }
+
+    /// Suspends the currently executing context and makes `exec_context` the top
+    /// of the stack.
+    pub(crate) fn push_context(&mut self, exec_context: ExecContext) {
+        let suspended = core::mem::replace(&mut self.exec_context, exec_context);
+        self.suspended_contexts.push(suspended);
+    }
+
+    /// Removes the top of the stack and resumes the context it suspended.
+    ///
+    /// Returns `None` if no context has been pushed.
+    pub(crate) fn pop_context(&mut self) -> Option<ExecContext> {
+        let resumed = self.suspended_contexts.pop()?;
+        Some(core::mem::replace(&mut self.exec_context, resumed))
+    }
+
+    /// Settles the storage deposit for the storage of `callee` growing or shrinking
+    /// from `old_len` to `new_len` bytes.
+    ///
+    /// Additionally stored bytes are charged from the free balance of the caller
+    /// and held for `callee`, freed bytes are refunded to the caller from the
+    /// deposit held for `callee`.
+    ///
+    /// # Errors
+    ///
+    /// If additional bytes are stored and there is no caller or the caller cannot
+    /// pay the deposit.
+    fn settle_storage_deposit(
+        &mut self,
+        callee: &[u8],
+        old_len: usize,
+        new_len: usize,
     ) -> Result {
-        unimplemented!("off-chain environment does not yet support `call`");
+        let per_byte = self.chain_spec.storage_deposit_per_byte;
+        if per_byte == 0 || old_len == new_len {
+            return Ok(())
+        }
+        let caller = self
+            .exec_context
+            .caller
+            .as_ref()
+            .map(|c| c.as_bytes().to_vec());
+        let deposit = self
+            .database
+            .get_storage_deposit(callee)
+            .unwrap_or_default();
+        if new_len > old_len {
+            let caller = caller.ok_or(Error::TransferFailed)?;
+            let charge = per_byte.saturating_mul((new_len - old_len) as Balance);
+            let caller_balance = self
+                .database
+                .get_balance(&caller)
+                .unwrap_or_default()
+                .checked_sub(charge)
+                .ok_or(Error::TransferFailed)?;
+            self.database.set_balance(&caller, caller_balance);
+            self.database
+                .set_storage_deposit(callee, deposit.saturating_add(charge));
+        } else if let Some(caller) = caller {
+            let refund = per_byte
+                .saturating_mul((old_len - new_len) as Balance)
+                .min(deposit);
+            let caller_balance = self.database.get_balance(&caller).unwrap_or_default();
+            self.database
+                .set_balance(&caller, caller_balance.saturating_add(refund));
+            self.database.set_storage_deposit(callee, deposit - refund);
+        }
+        Ok(())
+    }
+
+    /// Refunds the storage deposit for `len` bytes removed from the storage of
+    /// `callee` to the caller.
+    fn refund_storage_deposit(&mut self, callee: &[u8], len: usize) {
+        self.settle_storage_deposit(callee, len, 0)
+            .expect("refunding the storage deposit cannot fail");
+    }
+
+    /// Asserts that the address of the `role` account has the address length of
+    /// the chain.
+    fn assert_address_len(&self, role: &str, address: &[u8]) {
+        assert_eq!(
+            address.len(),
+            self.chain_spec.address_len,
+            "the {role} address has {} bytes, but the addresses of the chain have {} bytes",
+            address.len(),
+            self.chain_spec.address_len,
+        );
+    }
+
+    /// Removes the code hash and storage of the terminated contract at `account`.
+    fn remove_contract(&mut self, account: &[u8]) {
+        let removed_storage = self.database.remove_contract(account);
+        self.forget_contract(account, removed_storage);
+    }
+
+    /// Forgets the `removed_storage` cells and the contract registration of the
+    /// removed contract at `account`.
+    pub(crate) fn forget_contract(
+        &mut self,
+        account: &[u8],
+        removed_storage: Vec<(Vec<u8>, Vec<u8>)>,
+    ) {
+        let account_id = AccountId::from_bytes(account);
+        for (key, _) in removed_storage {
+            let _ = self
+                .debug_info
+                .remove_cell_for_account(account_id.clone(), key);
+        }
+        self.exec_context.contracts.retain(|c| &c[..] != account);
+    }
+
+    /// Charges the weight of the host function named `host_fn` to the current
+    /// contract execution and records the call.
+    ///
+    /// # Panics
+    ///
+    /// If the currently executing contract runs out of gas. All of its gas is
+    /// consumed in that case.
+    fn charge_gas(&mut self, host_fn: &'static str) {
+        self.debug_info.inc_host_fn_calls(host_fn);
+        let weight = self.gas_model.weight_of(host_fn);
+        let gas_consumed = self.exec_context.gas_consumed.saturating_add(weight);
+        let gas_ceiling = self.gas_ceiling();
+        if gas_consumed > gas_ceiling {
+            let gas_left = gas_ceiling.saturating_sub(self.exec_context.gas_consumed);
+            self.exec_context.gas_consumed = gas_ceiling;
+            panic!(
+                "out of gas: `{host_fn}` needs {weight} gas, but the contract execution \
+                 has only {gas_left} gas left"
+            )
+        }
+        self.exec_context.gas_consumed = gas_consumed;
+    }
+
+    /// Applies the `flags` of a call from `caller` into `callee` before the call.
+    ///
+    /// Returns the input the callee is invoked with.
+    ///
+    /// # Errors
+    ///
+    /// If `callee` is already executing and does not allow to be re-entered.
+    fn apply_code_flags_before_call(
+        &mut self,
+        caller: &[u8],
+        callee: &[u8],
+        flags: CallFlags,
+        input: &[u8],
+    ) -> core::result::Result<Vec<u8>, Error> {
+        // The contract executing at the top level has not been entered via `call`,
+        // but must be protected against reentrancy all the same.
+        if self.contracts.entrance_count.is_empty() {
+            self.contracts.entrance_count.insert(caller.to_vec(), 1);
+        }
+        self.contracts
+            .allow_reentry
+            .insert(caller.to_vec(), flags.contains(ALLOW_REENTRY));
+
+        let allows_reentry = self
+            .contracts
+            .allow_reentry
+            .get(callee)
+            .copied()
+            .unwrap_or(false);
+        let exceeds_max_depth =
+            self.contracts.call_depth() >= self.chain_spec.max_call_depth;
+        let denies_reentry = self.contracts.entrance_count(callee) > 0 && !allows_reentry;
+        if denies_reentry {
+            let trace = self
+                .suspended_contexts
+                .iter()
+                .chain(core::iter::once(&self.exec_context))
+                .filter_map(|exec_context| exec_context.callee.clone())
+                .chain(core::iter::once(AccountId::from_bytes(callee)))
+                .collect();
+            self.debug_info.record_reentrancy_trace(trace);
+        }
+        if exceeds_max_depth || denies_reentry {
+            self.contracts.allow_reentry.remove(caller);
+            self.release_outermost_caller(caller);
+            return Err(Error::CalleeTrapped)
+        }
+        *self
+            .contracts
+            .entrance_count
+            .entry(callee.to_vec())
+            .or_insert(0) += 1;
+
+        let input = if flags.contains(FORWARD_INPUT) {
+            core::mem::take(&mut self.exec_context.input)
+        } else if flags.contains(CLONE_INPUT) {
+            self.exec_context.input.clone()
+        } else {
+            input.to_vec()
+        };
+        Ok(input)
+    }
+
+    /// Applies the `flags` of a call from `caller` into `callee` after the call
+    /// returned `output`.
+    fn apply_code_flags_after_call(
+        &mut self,
+        caller: &[u8],
+        callee: &[u8],
+        flags: CallFlags,
+        output: &[u8],
+    ) {
+        if flags.contains(TAIL_CALL) {
+            self.exec_context.output = output.to_vec();
+        }
+        self.contracts.allow_reentry.remove(caller);
+        self.leave_contract(caller, callee);
+    }
+
+    /// Accounts for the constructor of the contract at `address` being executed
+    /// on behalf of `caller`.
+    ///
+    /// Constructor frames count toward the `max_call_depth` of the chain spec just
+    /// like the frames of calls do. Fails with `CalleeTrapped` if the maximum call
+    /// depth has been reached.
+    fn enter_constructor(&mut self, caller: &[u8], address: &[u8]) -> Result {
+        // The contract executing at the top level has not been entered via `call`,
+        // but occupies a frame all the same.
+        if self.contracts.entrance_count.is_empty() {
+            self.contracts.entrance_count.insert(caller.to_vec(), 1);
+        }
+        if self.contracts.call_depth() >= self.chain_spec.max_call_depth {
+            self.release_outermost_caller(caller);
+            return Err(Error::CalleeTrapped)
+        }
+        *self
+            .contracts
+            .entrance_count
+            .entry(address.to_vec())
+            .or_insert(0) += 1;
+        Ok(())
+    }
+
+    /// Stops accounting for a frame of `callee`, which was entered by `caller`.
+    fn leave_contract(&mut self, caller: &[u8], callee: &[u8]) {
+        if let Some(count) = self.contracts.entrance_count.get_mut(callee) {
+            *count -= 1;
+            if *count == 0 {
+                self.contracts.entrance_count.remove(callee);
+            }
+        }
+        self.release_outermost_caller(caller);
+    }
+
+    /// Stops accounting for the top level contract once `caller` is the only
+    /// contract left executing.
+    fn release_outermost_caller(&mut self, caller: &[u8]) {
+        if self.contracts.entrance_count.len() == 1
+            && self.contracts.entrance_count(caller) == 1
+        {
+            self.contracts.entrance_count.remove(caller);
+        }
     }
 
     /// Emulates gas price calculation.
-    pub fn weight_to_fee(&self, gas: u64, output: &mut &mut [u8]) {
-        let fee = self.chain_spec.gas_price.saturating_mul(gas.into());
+    ///
+    /// The `ref_time` of `weight` is priced at the `gas_price` and its
+    /// `proof_size` at the `storage_price` of the chain spec. The fee saturates at
+    /// `Balance::MAX`, see [`Engine::weight_to_fee_checked`].
+    pub fn weight_to_fee(&mut self, weight: Weight, output: &mut &mut [u8]) {
+        self.charge_gas("weight_to_fee");
+        let fee = self
+            .chain_spec
+            .gas_price
+            .saturating_mul(weight.ref_time.into())
+            .saturating_add(
+                self.chain_spec
+                    .storage_price
+                    .saturating_mul(weight.proof_size.into()),
+            );
         let fee: Vec<u8> = scale::Encode::encode(&fee);
         set_output(output, &fee[..])
     }
 
+    /// Returns the fee for `weight` like `weight_to_fee`, or `None` if the fee
+    /// overflows.
+    pub fn weight_to_fee_checked(&self, weight: Weight) -> Option<Balance> {
+        let ref_time_fee = self
+            .chain_spec
+            .gas_price
+            .checked_mul(weight.ref_time.into())?;
+        let proof_size_fee = self
+            .chain_spec
+            .storage_price
+            .checked_mul(weight.proof_size.into())?;
+        ref_time_fee.checked_add(proof_size_fee)
+    }
+
+    /// Emulates the fee calculation for a two-dimensional weight consisting of
+    /// `ref_time` and `proof_size`, see [`Engine::weight_to_fee`].
+    pub fn weight_to_fee_v2(
+        &mut self,
+        ref_time: u64,
+        proof_size: u64,
+        output: &mut &mut [u8],
+    ) {
+        self.weight_to_fee(Weight::from_parts(ref_time, proof_size), output)
+    }
+
     /// Calls the chain extension method registered at `func_id` with `input`.
+    ///
+    /// Fails with `UnregisteredChainExtension` if no method is registered at
+    /// `func_id`. A status code injected through the `FaultInjector` is returned
+    /// with an empty output instead of calling the method.
     pub fn call_chain_extension(
         &mut self,
         func_id: u32,
         input: &[u8],
         output: &mut &mut [u8],
-    ) {
+    ) -> core::result::Result<(), crate::Error> {
+        self.charge_gas("call_chain_extension");
+        if let Some(status_code) = self.fault_injector.take_chain_extension_failure() {
+            let res: (u32, &[u8]) = (status_code, &[]);
+            let decoded: Vec<u8> = scale::Encode::encode(&res);
+            set_output(output, &decoded[..]);
+            return Ok(())
+        }
         let encoded_input = input.encode();
-        let (status_code, out) = self
-            .chain_extension_handler
-            .eval(func_id, &encoded_input)
-            .unwrap_or_else(|error| {
-                panic!(
-                    "Encountered unexpected missing chain extension method: {error:?}"
-                );
-            });
+        let (status_code, out) =
+            self.chain_extension_handler.eval(func_id, &encoded_input)?;
         let res = (status_code, out);
         let decoded: Vec<u8> = scale::Encode::encode(&res);
-        set_output(output, &decoded[..])
+        set_output(output, &decoded[..]);
+        Ok(())
+    }
+
+    /// Dispatches the SCALE encoded runtime `call` to the dispatchable registered
+    /// for its pallet and call index.
+    ///
+    /// Fails with `CallRuntimeFailed` if no dispatchable is registered for the call
+    /// or if the dispatchable fails. The changes of a failed call are reverted.
+    pub fn call_runtime(&mut self, call: &[u8]) -> Result {
+        self.charge_gas("call_runtime");
+        let (index, args) = match call {
+            [pallet_index, call_index, args @ ..] => ((*pallet_index, *call_index), args),
+            _ => return Err(Error::CallRuntimeFailed),
+        };
+        let dispatchable = *self
+            .runtime_calls
+            .get(&index)
+            .ok_or(Error::CallRuntimeFailed)?;
+        let snapshot = self.database.snapshot();
+        match dispatchable(self, args) {
+            Ok(()) => {
+                self.database.release(snapshot);
+                Ok(())
+            }
+            Err(_) => {
+                self.database.restore(snapshot);
+                Err(Error::CallRuntimeFailed)
+            }
+        }
     }
 
     /// Recovers the compressed ECDSA public key for given `signature` and `message_hash`,
@@ -484,6 +1717,7 @@ impl Engine {
             Message,
             SECP256K1,
         };
+        self.charge_gas("ecdsa_recover");
 
         // In most implementations, the v is just 0 or 1 internally, but 27 was added
         // as an arbitrary number for signing Bitcoin messages and Ethereum adopted that
@@ -513,6 +1747,113 @@ impl Engine {
             Err(_) => Err(Error::EcdsaRecoveryFailed),
         }
     }
+
+    /// Converts the compressed ECDSA public key `pubkey` into an Ethereum address,
+    /// and stores the result in `output`.
+    pub fn ecdsa_to_eth_address(
+        &mut self,
+        pubkey: &[u8; 33],
+        output: &mut [u8; 20],
+    ) -> Result {
+        self.charge_gas("ecdsa_to_eth_address");
+        let pubkey = secp256k1::PublicKey::from_slice(pubkey)
+            .map_err(|_| Error::EcdsaRecoveryFailed)?;
+        // The uncompressed form is prefixed by a tag byte which is not hashed.
+        let uncompressed = pubkey.serialize_uncompressed();
+        let mut hash = [0; 32];
+        super::hashing::keccak_256(&uncompressed[1..], &mut hash);
+        output.copy_from_slice(&hash[12..]);
+        Ok(())
+    }
+
+    /// Verifies the sr25519 `signature` of `message` against `pub_key`.
+    pub fn sr25519_verify(
+        &mut self,
+        signature: &[u8; 64],
+        message: &[u8],
+        pub_key: &[u8; 32],
+    ) -> Result {
+        use schnorrkel::{
+            PublicKey,
+            Signature,
+        };
+        self.charge_gas("sr25519_verify");
+
+        // The signing context defaults to "substrate" in Substrate.
+        let context = b"substrate";
+        let signature =
+            Signature::from_bytes(signature).map_err(|_| Error::Sr25519VerifyFailed)?;
+        let public_key =
+            PublicKey::from_bytes(pub_key).map_err(|_| Error::Sr25519VerifyFailed)?;
+        public_key
+            .verify_simple(context, message, &signature)
+            .map_err(|_| Error::Sr25519VerifyFailed)
+    }
+
+    /// Verifies the ed25519 `signature` of `message` against `pub_key`.
+    pub fn ed25519_verify(
+        &mut self,
+        signature: &[u8; 64],
+        message: &[u8],
+        pub_key: &[u8; 32],
+    ) -> Result {
+        use ed25519_dalek::{
+            Signature,
+            Verifier as _,
+            VerifyingKey,
+        };
+        self.charge_gas("ed25519_verify");
+
+        let signature = Signature::from_bytes(signature);
+        let public_key =
+            VerifyingKey::from_bytes(pub_key).map_err(|_| Error::Ed25519VerifyFailed)?;
+        public_key
+            .verify(message, &signature)
+            .map_err(|_| Error::Ed25519VerifyFailed)
+    }
+}
+
+/// Returns the reason of a contract execution trapping with the panic `payload`.
+///
+/// Must not be called with the payload of a successful termination.
+pub(crate) fn trap_reason(payload: &(dyn Any + Send)) -> TrapReason {
+    if termination_result(payload).is_some() {
+        return TrapReason::TerminationFailed
+    }
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| String::from("the panic payload is not a message"));
+    TrapReason::Panic(message)
+}
+
+/// Returns `true` if `payload` is the panic payload of a successful `terminate`.
+pub(crate) fn is_successful_termination(payload: &(dyn Any + Send)) -> bool {
+    matches!(
+        termination_result(payload),
+        Some(TerminationResult::Terminated { .. })
+    )
+}
+
+/// Returns the outcome of `terminate` if `payload` is the panic payload of
+/// `terminate`.
+fn termination_result(payload: &(dyn Any + Send)) -> Option<TerminationResult<Balance>> {
+    payload.downcast_ref::<Vec<u8>>().and_then(|encoded| {
+        <TerminationResult<Balance> as scale::DecodeAll>::decode_all(&mut &encoded[..])
+            .ok()
+    })
+}
+
+/// Copies the `slice` into `output`.
+///
+/// Fails with `BufferTooSmall` if the slice is too large and does not fit.
+fn try_set_output(output: &mut &mut [u8], slice: &[u8]) -> Result {
+    if slice.len() > output.len() {
+        return Err(Error::BufferTooSmall)
+    }
+    output[..slice.len()].copy_from_slice(slice);
+    Ok(())
 }
 
 /// Copies the `slice` into `output`.
@@ -0,0 +1,59 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Failures to be injected into the next calls to selected host functions.
+///
+/// Every injected failure is consumed by the first call to the corresponding
+/// host function, the calls after that behave normally again.
+#[derive(Debug, Default, Clone)]
+pub struct FaultInjector {
+    /// Whether the next `transfer` fails.
+    transfer: bool,
+    /// The status code returned by the next chain extension call, if any.
+    chain_extension: Option<u32>,
+}
+
+impl FaultInjector {
+    /// Creates a new fault injector without any injected failures.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Makes the next `transfer` fail with `TransferFailed`.
+    pub fn fail_next_transfer(&mut self) {
+        self.transfer = true;
+    }
+
+    /// Makes the next chain extension call return `status` without invoking
+    /// the registered chain extension method.
+    pub fn fail_next_chain_extension(&mut self, status: u32) {
+        self.chain_extension = Some(status);
+    }
+
+    /// Removes all injected failures.
+    pub fn reset(&mut self) {
+        self.transfer = false;
+        self.chain_extension = None;
+    }
+
+    /// Consumes the injected `transfer` failure, if any.
+    pub(crate) fn take_transfer_failure(&mut self) -> bool {
+        core::mem::take(&mut self.transfer)
+    }
+
+    /// Consumes the injected chain extension status code, if any.
+    pub(crate) fn take_chain_extension_failure(&mut self) -> Option<u32> {
+        self.chain_extension.take()
+    }
+}
@@ -29,6 +29,16 @@ pub fn blake2b_256(input: &[u8], output: &mut [u8; 32]) {
     output.copy_from_slice(&result);
 }
 
+/// Conduct the BLAKE2 512-bit hash and place the result into `output`.
+pub fn blake2b_512(input: &[u8], output: &mut [u8; 64]) {
+    use ::blake2::digest::Digest as _;
+
+    let mut blake2 = ::blake2::Blake2b512::new();
+    blake2.update(input);
+    let result = blake2.finalize();
+    output.copy_from_slice(&result);
+}
+
 /// Conduct the BLAKE2 128-bit hash and place the result into `output`.
 pub fn blake2b_128(input: &[u8], output: &mut [u8; 16]) {
     use ::blake2::digest::{
@@ -66,6 +76,28 @@ pub fn sha2_256(input: &[u8], output: &mut [u8; 32]) {
     hasher.finalize_into(<&mut GenericArray<u8, _>>::from(&mut output[..]));
 }
 
+/// Conduct the SHA-2 512-bit hash and place the result into `output`.
+pub fn sha2_512(input: &[u8], output: &mut [u8; 64]) {
+    use sha2::{
+        digest::generic_array::GenericArray,
+        Digest as _,
+    };
+    let mut hasher = sha2::Sha512::new();
+    hasher.update(input);
+    hasher.finalize_into(<&mut GenericArray<u8, _>>::from(&mut output[..]));
+}
+
+/// Conduct the RIPEMD 160-bit hash and place the result into `output`.
+pub fn ripemd160(input: &[u8], output: &mut [u8; 20]) {
+    use ripemd::{
+        digest::generic_array::GenericArray,
+        Digest as _,
+    };
+    let mut hasher = ripemd::Ripemd160::new();
+    hasher.update(input);
+    hasher.finalize_into(<&mut GenericArray<u8, _>>::from(&mut output[..]));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -100,6 +132,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hash_sha2_512() {
+        let mut output = [0x00_u8; 64];
+        sha2_512(TEST_INPUT, &mut output);
+        assert_eq!(
+            output,
+            [
+                108, 246, 138, 138, 141, 115, 134, 85, 193, 49, 2, 47, 33, 151, 176, 82,
+                13, 121, 18, 26, 203, 145, 214, 55, 47, 41, 115, 176, 118, 61, 35, 206,
+                61, 165, 252, 186, 117, 225, 91, 25, 203, 200, 234, 207, 228, 28, 218,
+                144, 190, 28, 187, 169, 178, 23, 225, 4, 115, 0, 29, 145, 203, 56, 247,
+                213
+            ]
+        );
+    }
+
     #[test]
     fn test_hash_blake2_256() {
         let mut output = [0x00_u8; 32];
@@ -125,4 +173,32 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_hash_blake2_512() {
+        let mut output = [0x00_u8; 64];
+        blake2b_512(TEST_INPUT, &mut output);
+        assert_eq!(
+            output,
+            [
+                5, 48, 44, 146, 68, 58, 111, 93, 198, 180, 15, 19, 105, 160, 20, 104, 11,
+                119, 171, 123, 227, 210, 132, 79, 108, 119, 86, 216, 82, 236, 56, 147,
+                83, 142, 148, 81, 130, 13, 181, 73, 67, 94, 18, 120, 107, 242, 255, 103,
+                11, 25, 46, 170, 92, 23, 168, 63, 22, 102, 146, 204, 244, 74, 153, 122
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hash_ripemd160() {
+        let mut output = [0x00_u8; 20];
+        ripemd160(b"", &mut output);
+        assert_eq!(
+            output,
+            [
+                156, 17, 133, 165, 197, 233, 252, 84, 97, 40, 8, 151, 126, 232, 245, 72,
+                178, 37, 141, 49
+            ]
+        );
+    }
 }
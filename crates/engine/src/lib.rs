@@ -19,18 +19,36 @@
 
 pub mod ext;
 pub mod test_api;
+pub mod well_known;
 
+mod address_deriver;
 mod chain_extension;
+mod contracts;
 mod database;
 mod exec_context;
+mod fault_injection;
 mod hashing;
 mod types;
 
 #[cfg(test)]
 mod tests;
 
-pub use chain_extension::ChainExtension;
-pub use types::AccountError;
+pub use address_deriver::{
+    AddressDeriver,
+    Blake2AddressDeriver,
+};
+pub use chain_extension::{
+    ChainExtension,
+    ChainExtensionObject,
+    Extension,
+};
+pub use exec_context::Frame;
+pub use fault_injection::FaultInjector;
+pub use types::{
+    AccountError,
+    AccountId,
+    Weight,
+};
 
 use derive_more::From;
 
@@ -44,4 +62,12 @@ pub enum Error {
     UninitializedExecutionContext,
     #[from(ignore)]
     UnregisteredChainExtension,
+    #[from(ignore)]
+    DecreasingBlockNumber,
+    #[from(ignore)]
+    DecreasingBlockTimestamp,
+    #[from(ignore)]
+    CodeNotFound,
+    #[from(ignore)]
+    CodeInUse,
 }
@@ -13,7 +13,24 @@
 // limitations under the License.
 
 use crate::{
-    ext::Engine,
+    contracts::{
+        Contract,
+        ContractStorage,
+    },
+    database::SnapshotId,
+    exec_context::{
+        ExecContext,
+        Frame,
+    },
+    ext::{
+        is_successful_termination,
+        trap_reason,
+        Engine,
+        ExecReturn,
+        RuntimeCall,
+        TopicHasher,
+        TrapReason,
+    },
     types::{
         AccountId,
         Balance,
@@ -22,18 +39,51 @@ use crate::{
     },
     AccountError,
     Error,
+    FaultInjector,
+};
+use std::{
+    collections::HashMap,
+    panic::{
+        catch_unwind,
+        resume_unwind,
+        AssertUnwindSafe,
+    },
 };
-use std::collections::HashMap;
+
+/// Returns the topic under which an event field of `value` is recorded by the
+/// default runtime, i.e. with topics hashed by BLAKE2 256-bit.
+///
+/// Use [`Engine::topic_for`] to hash with the `topic_hasher` of the chain spec.
+pub fn topic_for<T: scale::Encode>(value: &T) -> Vec<u8> {
+    TopicHasher::Blake2x256.topic_for(value)
+}
 
 /// Record for an emitted event.
 #[derive(Debug, Clone)]
 pub struct EmittedEvent {
+    /// The position of the event among all events emitted since the engine was
+    /// created or reset, starting at `0`.
+    pub index: u64,
+    /// The number of the block in which the event was emitted.
+    pub block_number: BlockNumber,
     /// Recorded topics of the emitted event.
     pub topics: Vec<Vec<u8>>,
     /// Recorded encoding of the emitted event.
     pub data: Vec<u8>,
 }
 
+impl EmittedEvent {
+    /// Returns `true` if `topic` is one of the recorded topics of the event.
+    pub fn has_topic(&self, topic: &[u8]) -> bool {
+        self.topics.iter().any(|recorded| &recorded[..] == topic)
+    }
+
+    /// Decodes the recorded encoding of the event as `T`.
+    pub fn decode_data<T: scale::Decode>(&self) -> Result<T, scale::Error> {
+        <T as scale::Decode>::decode(&mut &self.data[..])
+    }
+}
+
 #[derive(Clone)]
 pub struct RecordedDebugMessages {
     debug_messages: Vec<String>,
@@ -73,18 +123,43 @@ impl IntoIterator for RecordedDebugMessages {
     }
 }
 
+/// The state of an engine taken by [`Engine::with_snapshot`] and
+/// [`Engine::dry_run`] to revert to.
+struct EngineSnapshot {
+    database: SnapshotId,
+    exec_context: ExecContext,
+    suspended_contexts: Vec<ExecContext>,
+    debug_info: DebugInfo,
+    contracts: ContractStorage,
+    fault_injector: FaultInjector,
+}
+
 /// Recorder for relevant interactions with this crate.
+#[derive(Clone)]
 pub struct DebugInfo {
     /// Emitted events recorder.
     emitted_events: Vec<EmittedEvent>,
+    /// The number of events recorded since the recorder was created or reset,
+    /// including events which have been cleared or taken since.
+    events_recorded: u64,
     /// Emitted print messages recorder.
     emitted_debug_messages: RecordedDebugMessages,
+    /// The total length in bytes of the recorded debug messages.
+    debug_buffer_len: usize,
+    /// Whether debug messages were dropped for not fitting into the debug buffer.
+    debug_truncated: bool,
     /// The total number of reads to the storage.
     count_reads: HashMap<AccountId, usize>,
     /// The total number of writes to the storage.
     count_writes: HashMap<AccountId, usize>,
     /// The number of storage cells used by each account id.
     cells_per_account: HashMap<AccountId, HashMap<Vec<u8>, bool>>,
+    /// The number of calls to each host function of the engine.
+    host_fn_calls: HashMap<&'static str, u32>,
+    /// The number of (reads, writes) of each storage key of each account id.
+    key_access_counts: HashMap<(AccountId, Vec<u8>), (u32, u32)>,
+    /// The call chain of the last call denied for re-entering a contract.
+    last_reentrancy_trace: Option<Vec<AccountId>>,
 }
 
 impl Default for DebugInfo {
@@ -98,10 +173,16 @@ impl DebugInfo {
     pub fn new() -> Self {
         Self {
             emitted_events: Vec::new(),
+            events_recorded: 0,
             emitted_debug_messages: RecordedDebugMessages::new(),
+            debug_buffer_len: 0,
+            debug_truncated: false,
             count_reads: HashMap::new(),
             count_writes: HashMap::new(),
             cells_per_account: HashMap::new(),
+            host_fn_calls: HashMap::new(),
+            key_access_counts: HashMap::new(),
+            last_reentrancy_trace: None,
         }
     }
 
@@ -110,26 +191,65 @@ impl DebugInfo {
         self.count_reads.clear();
         self.count_writes.clear();
         self.emitted_events.clear();
+        self.events_recorded = 0;
         self.emitted_debug_messages.clear();
+        self.debug_buffer_len = 0;
+        self.debug_truncated = false;
         self.cells_per_account.clear();
+        self.host_fn_calls.clear();
+        self.key_access_counts.clear();
+        self.last_reentrancy_trace = None;
     }
 
-    /// Increases the number of storage writes for the supplied account by one.
-    pub fn inc_writes(&mut self, account_id: AccountId) {
+    /// Increases the number of storage writes for the supplied account and
+    /// `key` by one.
+    pub fn inc_writes(&mut self, account_id: AccountId, key: &[u8]) {
+        self.key_access_counts
+            .entry((account_id.clone(), key.to_vec()))
+            .or_insert((0, 0))
+            .1 += 1;
         self.count_writes
             .entry(account_id)
             .and_modify(|v| *v += 1)
             .or_insert(1);
     }
 
-    /// Increases the number of storage reads for the supplied account by one.
-    pub fn inc_reads(&mut self, account_id: AccountId) {
+    /// Increases the number of storage reads for the supplied account and
+    /// `key` by one.
+    pub fn inc_reads(&mut self, account_id: AccountId, key: &[u8]) {
+        self.key_access_counts
+            .entry((account_id.clone(), key.to_vec()))
+            .or_insert((0, 0))
+            .0 += 1;
         self.count_reads
             .entry(account_id)
             .and_modify(|v| *v += 1)
             .or_insert(1);
     }
 
+    /// Returns the number of (reads, writes) of `key` in the storage of the
+    /// supplied account.
+    pub fn key_access_counts(&self, account_id: &AccountId, key: &[u8]) -> (u32, u32) {
+        self.key_access_counts
+            .get(&(account_id.clone(), key.to_vec()))
+            .copied()
+            .unwrap_or((0, 0))
+    }
+
+    /// Records the call chain of a call denied for re-entering a contract.
+    ///
+    /// The chain starts with the outermost callee and ends with the re-entered
+    /// contract.
+    pub fn record_reentrancy_trace(&mut self, trace: Vec<AccountId>) {
+        self.last_reentrancy_trace = Some(trace);
+    }
+
+    /// Returns the call chain of the last call denied for re-entering a
+    /// contract, if any.
+    pub fn last_reentrancy_trace(&self) -> Option<Vec<AccountId>> {
+        self.last_reentrancy_trace.clone()
+    }
+
     /// Records that a cell exists for an account under `key`.
     ///
     /// Calling this function multiple times won't change the fact that only
@@ -161,23 +281,108 @@ impl DebugInfo {
             .unwrap_or(None)
     }
 
+    /// Increases the number of calls to the host function `host_fn` by one.
+    pub fn inc_host_fn_calls(&mut self, host_fn: &'static str) {
+        *self.host_fn_calls.entry(host_fn).or_insert(0) += 1;
+    }
+
+    /// Returns the number of calls to the host function `host_fn`.
+    pub fn host_fn_calls(&self, host_fn: &str) -> u32 {
+        self.host_fn_calls.get(host_fn).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of calls to each host function which has been called.
+    pub fn all_host_fn_calls(&self) -> &HashMap<&'static str, u32> {
+        &self.host_fn_calls
+    }
+
     /// Records a debug message.
     pub fn record_debug_message(&mut self, message: String) {
+        self.debug_buffer_len += message.len();
         self.emitted_debug_messages.record(message);
     }
 
+    /// Returns the total length in bytes of the recorded debug messages.
+    pub fn debug_buffer_len(&self) -> usize {
+        self.debug_buffer_len
+    }
+
+    /// Records that a debug message was dropped for not fitting into the debug
+    /// buffer.
+    pub fn record_debug_truncation(&mut self) {
+        self.debug_truncated = true;
+    }
+
+    /// Returns `true` if debug messages were dropped for not fitting into the
+    /// debug buffer.
+    pub fn debug_truncated(&self) -> bool {
+        self.debug_truncated
+    }
+
     /// Records an event.
     pub fn record_event(&mut self, event: EmittedEvent) {
         self.emitted_events.push(event);
+        self.events_recorded += 1;
+    }
+
+    /// Returns the number of events recorded since the recorder was created or
+    /// reset, i.e. the index of the next recorded event.
+    pub fn events_recorded(&self) -> u64 {
+        self.events_recorded
+    }
+
+    /// Removes all recorded events.
+    pub fn clear_events(&mut self) {
+        self.emitted_events.clear();
+    }
+
+    /// Removes the recorded events matching `pred` and returns them in order of
+    /// emission.
+    pub fn take_events_matching(
+        &mut self,
+        pred: impl Fn(&EmittedEvent) -> bool,
+    ) -> Vec<EmittedEvent> {
+        let (taken, kept) = core::mem::take(&mut self.emitted_events)
+            .into_iter()
+            .partition(|event| pred(event));
+        self.emitted_events = kept;
+        taken
+    }
+
+    /// Returns the recorded events which carry `topic`, in order of emission.
+    pub fn events_with_topic(&self, topic: &[u8]) -> Vec<&EmittedEvent> {
+        self.emitted_events
+            .iter()
+            .filter(|event| event.has_topic(topic))
+            .collect()
     }
 }
 
 impl Engine {
     /// Resets the environment.
     pub fn initialize_or_reset(&mut self) {
+        self.reset();
+    }
+
+    /// Clears the storage, balances, execution context, recorded debug info,
+    /// deployed contracts and injected failures.
+    ///
+    /// The chain specification, the registered chain extensions and the registered
+    /// runtime calls are kept.
+    pub fn reset(&mut self) {
         self.exec_context.reset();
         self.database.clear();
         self.debug_info.reset();
+        self.contracts.clear();
+        self.fault_injector.reset();
+        self.suspended_contexts.clear();
+    }
+
+    /// Resets the engine to the state of a newly created `Engine`, including
+    /// the chain specification, the registered chain extensions and the
+    /// registered runtime calls.
+    pub fn reset_all(&mut self) {
+        *self = Self::new();
     }
 
     /// Returns the total number of reads and writes of the contract's storage.
@@ -188,6 +393,106 @@ impl Engine {
         (*reads, *writes)
     }
 
+    /// Returns the number of reads and writes of `key` in the contract's storage.
+    pub fn key_access_counts(&self, account_id: &[u8], key: &[u8]) -> (u32, u32) {
+        self.debug_info
+            .key_access_counts(&AccountId::from_bytes(account_id), key)
+    }
+
+    /// Returns the call chain of the last call denied for re-entering a
+    /// contract, if any.
+    pub fn last_reentrancy_trace(&self) -> Option<Vec<AccountId>> {
+        self.debug_info.last_reentrancy_trace()
+    }
+
+    /// Removes everything stored for `account`, e.g. to set up a fresh scenario.
+    ///
+    /// Removes its balances, the storage deposit held for it, its nonce, its code
+    /// hash, its storage and its registration as known contract.
+    pub fn remove_account(&mut self, account: &[u8]) {
+        let removed_storage = self.database.clear_account(account);
+        self.forget_contract(account, removed_storage);
+    }
+
+    /// Returns all entries of the contract storage of `account_id`.
+    ///
+    /// The entries are yielded as `(key, value)` pairs sorted by their key.
+    pub fn iter_contract_storage(
+        &self,
+        account_id: &[u8],
+    ) -> impl Iterator<Item = (Vec<u8>, Vec<u8>)> + '_ {
+        self.database
+            .iter_contract_storage(account_id)
+            .map(|(key, value)| (key.clone(), value.clone()))
+    }
+
+    /// Runs `f` on the engine, reverting its state if `f` panics.
+    ///
+    /// The reverted state consists of the database, the execution contexts,
+    /// the recorded debug info, the contracts bookkeeping and the injected
+    /// failures. The chain spec and the registered handlers are kept.
+    pub fn with_snapshot<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Engine) -> R,
+    {
+        let snapshot = self.snapshot_state();
+        match catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(result) => {
+                self.database.release(snapshot.database);
+                result
+            }
+            Err(payload) => {
+                self.restore_state(snapshot);
+                resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Runs `f` on the engine and reverts its state afterwards, whether `f`
+    /// returns or panics.
+    ///
+    /// The same state as by [`Engine::with_snapshot`] is reverted, so events,
+    /// debug messages and storage accesses recorded by `f` are discarded too.
+    /// Allows probing the outcome of an operation without its side effects.
+    pub fn dry_run<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Engine) -> R,
+    {
+        let snapshot = self.snapshot_state();
+        let result = catch_unwind(AssertUnwindSafe(|| f(self)));
+        self.restore_state(snapshot);
+        result.unwrap_or_else(|payload| resume_unwind(payload))
+    }
+
+    /// Takes a snapshot of the state reverted by [`Engine::with_snapshot`].
+    fn snapshot_state(&mut self) -> EngineSnapshot {
+        EngineSnapshot {
+            database: self.database.snapshot(),
+            exec_context: self.exec_context.clone(),
+            suspended_contexts: self.suspended_contexts.clone(),
+            debug_info: self.debug_info.clone(),
+            contracts: self.contracts.clone(),
+            fault_injector: self.fault_injector.clone(),
+        }
+    }
+
+    /// Restores the state the engine had when `snapshot` was taken.
+    fn restore_state(&mut self, snapshot: EngineSnapshot) {
+        self.database.restore(snapshot.database);
+        self.exec_context = snapshot.exec_context;
+        self.suspended_contexts = snapshot.suspended_contexts;
+        self.debug_info = snapshot.debug_info;
+        self.contracts = snapshot.contracts;
+        self.fault_injector = snapshot.fault_injector;
+    }
+
+    /// Returns the number of calls to the host function `host_fn`.
+    ///
+    /// The hash functions are not counted since they are not called on an engine.
+    pub fn host_fn_calls(&self, host_fn: &str) -> u32 {
+        self.debug_info.host_fn_calls(host_fn)
+    }
+
     /// Returns the total number of reads executed.
     pub fn count_reads(&self) -> usize {
         self.debug_info.count_reads.values().sum()
@@ -203,6 +508,16 @@ impl Engine {
         self.exec_context.caller = Some(caller.into());
     }
 
+    /// Sets the origin of the transaction.
+    pub fn set_origin(&mut self, origin: Vec<u8>) {
+        self.exec_context.origin = Some(origin.into());
+    }
+
+    /// Sets whether the origin of the transaction is root.
+    pub fn set_root(&mut self, is_root: bool) {
+        self.exec_context.is_root = is_root;
+    }
+
     /// Sets a known contract by adding it to a vector of known contracts accounts
     pub fn set_contract(&mut self, caller: Vec<u8>) {
         self.exec_context.contracts.push(caller);
@@ -213,6 +528,75 @@ impl Engine {
         self.exec_context.callee = Some(callee.into());
     }
 
+    /// Sets the input of the next call, e.g. the selector and the encoded
+    /// arguments of a message.
+    pub fn set_input(&mut self, data: &[u8]) {
+        self.exec_context.input = data.to_vec();
+    }
+
+    /// Registers a contract with the given entry points under `code_hash` and
+    /// deploys it at `account_id`.
+    ///
+    /// The entry points are invoked by the engine whenever the contract is
+    /// instantiated or called.
+    pub fn register_contract(
+        &mut self,
+        account_id: &[u8],
+        code_hash: &[u8],
+        deploy: fn(&mut Engine),
+        call: fn(&mut Engine),
+    ) {
+        self.upload_code(code_hash, deploy, call);
+        self.database.set_code_hash(account_id, code_hash);
+    }
+
+    /// Registers code with the given entry points under `code_hash` without
+    /// deploying it at any account.
+    ///
+    /// Contracts can then be instantiated from `code_hash`.
+    pub fn upload_code(
+        &mut self,
+        code_hash: &[u8],
+        deploy: fn(&mut Engine),
+        call: fn(&mut Engine),
+    ) {
+        self.contracts
+            .deployed
+            .insert(code_hash.to_vec(), Contract { deploy, call });
+    }
+
+    /// Removes the code registered under `code_hash`.
+    ///
+    /// # Errors
+    ///
+    /// - `CodeNotFound` if no code is registered under `code_hash`.
+    /// - `CodeInUse` if a contract is deployed with the code.
+    pub fn remove_code(&mut self, code_hash: &[u8]) -> Result<(), Error> {
+        if self.contracts.get(code_hash).is_none() {
+            return Err(Error::CodeNotFound)
+        }
+        let in_use = self.database.deployed_contracts().iter().any(|account| {
+            self.database.get_code_hash(account.as_bytes()).as_deref() == Some(code_hash)
+        });
+        if in_use {
+            return Err(Error::CodeInUse)
+        }
+        self.contracts.deployed.remove(code_hash);
+        Ok(())
+    }
+
+    /// Registers `dispatchable` to be executed by `call_runtime` for calls with the
+    /// given `pallet_index` and `call_index`.
+    pub fn register_runtime_call(
+        &mut self,
+        pallet_index: u8,
+        call_index: u8,
+        dispatchable: RuntimeCall,
+    ) {
+        self.runtime_calls
+            .insert((pallet_index, call_index), dispatchable);
+    }
+
     /// Returns the amount of storage cells used by the account `account_id`.
     ///
     /// Returns `None` if the `account_id` is non-existent.
@@ -228,9 +612,38 @@ impl Engine {
     }
 
     /// Advances the chain by a single block.
+    ///
+    /// # Panics
+    ///
+    /// If the block number or the block timestamp would overflow.
     pub fn advance_block(&mut self) {
-        self.exec_context.block_number += 1;
-        self.exec_context.block_timestamp += self.chain_spec.block_time;
+        self.advance_blocks(1)
+    }
+
+    /// Advances the chain by `n` blocks.
+    ///
+    /// # Panics
+    ///
+    /// If the block number or the block timestamp would overflow. The chain is
+    /// not advanced at all in this case.
+    pub fn advance_blocks(&mut self, n: BlockNumber) {
+        let block_number = self.exec_context.block_number;
+        let block_timestamp = self.exec_context.block_timestamp;
+        let block_time = self.chain_spec.block_time;
+        let new_block_number = block_number.checked_add(n).unwrap_or_else(|| {
+            panic!("advancing block {block_number} by {n} blocks overflows")
+        });
+        let new_block_timestamp = block_time
+            .checked_mul(BlockTimestamp::from(n))
+            .and_then(|elapsed| block_timestamp.checked_add(elapsed))
+            .unwrap_or_else(|| {
+                panic!(
+                    "advancing timestamp {block_timestamp} by {n} blocks of block time \
+                    {block_time} overflows"
+                )
+            });
+        self.exec_context.block_number = new_block_number;
+        self.exec_context.block_timestamp = new_block_timestamp;
     }
 
     /// Returns the callee, i.e. the currently executing contract.
@@ -238,9 +651,21 @@ impl Engine {
         self.exec_context.callee()
     }
 
-    /// Returns boolean value indicating whether the account is a contract
-    pub fn is_contract(&self, account_id: Vec<u8>) -> bool {
-        self.exec_context.contracts.contains(&account_id)
+    /// Returns the account of the callee, i.e. the currently executing contract.
+    ///
+    /// # Panics
+    ///
+    /// If no callee has been set.
+    pub fn callee(&self) -> AccountId {
+        self.exec_context
+            .callee
+            .clone()
+            .expect("no callee has been set")
+    }
+
+    /// Returns the account of the caller, if it has been set.
+    pub fn caller_account(&self) -> Option<AccountId> {
+        self.exec_context.caller.clone()
     }
 
     /// Returns the contents of the past performed environmental `debug_message` in order.
@@ -248,11 +673,84 @@ impl Engine {
         self.debug_info.emitted_debug_messages.clone()
     }
 
+    /// Returns `true` if debug messages were dropped for not fitting into the
+    /// debug buffer.
+    pub fn debug_truncated(&self) -> bool {
+        self.debug_info.debug_truncated()
+    }
+
     /// Returns the recorded emitted events in order.
     pub fn get_emitted_events(&self) -> impl Iterator<Item = EmittedEvent> {
         self.debug_info.emitted_events.clone().into_iter()
     }
 
+    /// Returns a copy of the recorded emitted events in order.
+    pub fn emitted_events(&self) -> Vec<EmittedEvent> {
+        self.debug_info.emitted_events.clone()
+    }
+
+    /// Removes all recorded emitted events, e.g. between the phases of a test.
+    pub fn clear_events(&mut self) {
+        self.debug_info.clear_events();
+    }
+
+    /// Removes the recorded emitted events matching `pred` and returns them in
+    /// order, leaving the other events recorded.
+    pub fn take_events_matching(
+        &mut self,
+        pred: impl Fn(&EmittedEvent) -> bool,
+    ) -> Vec<EmittedEvent> {
+        self.debug_info.take_events_matching(pred)
+    }
+
+    /// Returns the recorded emitted events which carry `topic`, in order.
+    pub fn events_with_topic(&self, topic: &[u8]) -> Vec<&EmittedEvent> {
+        self.debug_info.events_with_topic(topic)
+    }
+
+    /// Returns the topic under which an event field of `value` is recorded,
+    /// hashed with the `topic_hasher` of the chain spec.
+    pub fn topic_for<T: scale::Encode>(&self, value: &T) -> Vec<u8> {
+        self.chain_spec.topic_hasher.topic_for(value)
+    }
+
+    /// Returns the first recorded event whose encoding decodes as `E`.
+    ///
+    /// An event only decodes as `E` if its whole encoding is consumed.
+    pub fn find_event<E: scale::Decode>(&self) -> Option<E> {
+        self.debug_info
+            .emitted_events
+            .iter()
+            .find_map(|event| scale::DecodeAll::decode_all(&mut &event.data[..]).ok())
+    }
+
+    /// Asserts that an event equal to `expected` has been recorded.
+    ///
+    /// # Panics
+    ///
+    /// If none of the recorded events decodes as `E` and equals `expected`. The
+    /// panic message lists the recorded events which decode as `E`.
+    pub fn assert_event_emitted<E>(&self, expected: &E)
+    where
+        E: scale::Decode + PartialEq + core::fmt::Debug,
+    {
+        let decoded: Vec<E> = self
+            .debug_info
+            .emitted_events
+            .iter()
+            .filter_map(|event| scale::DecodeAll::decode_all(&mut &event.data[..]).ok())
+            .collect();
+        assert!(
+            decoded.iter().any(|event| event == expected),
+            "expected the event {:?} to be emitted, but the {} emitted events \
+             decoding as `{}` are {:?}",
+            expected,
+            decoded.len(),
+            core::any::type_name::<E>(),
+            decoded,
+        );
+    }
+
     /// Returns the current balance of `account_id`.
     pub fn get_balance(&self, account_id: Vec<u8>) -> Result<Balance, Error> {
         self.database
@@ -260,24 +758,479 @@ impl Engine {
             .ok_or(Error::Account(AccountError::NoAccountForId(account_id)))
     }
 
+    /// Returns `true` if `account` exists, i.e. if it is a contract or if its free
+    /// and reserved balance together are at least the minimum balance.
+    ///
+    /// An account without a recorded balance only exists if it is a contract.
+    pub fn account_exists(&self, account: &[u8]) -> bool {
+        if self.is_contract(account) {
+            return true
+        }
+        match self.database.get_balance(account) {
+            Some(free) => {
+                let reserved = self.database.get_reserved_balance(account).unwrap_or(0);
+                free.saturating_add(reserved) >= self.chain_spec.minimum_balance
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the free balance of `account` to `balance`.
+    pub fn set_account_balance(&mut self, account: &[u8], balance: Balance) {
+        self.database.set_balance(account, balance);
+    }
+
+    /// Returns the free balance of `account`, if it exists.
+    pub fn account_balance(&self, account: &[u8]) -> Option<Balance> {
+        self.database.get_balance(account)
+    }
+
+    /// Returns the sum of the free and reserved balances and the storage deposits
+    /// of all accounts.
+    pub fn total_issuance(&self) -> Balance {
+        self.database.total_issuance()
+    }
+
+    /// Returns the storage deposit held for the contract `account_id`.
+    pub fn storage_deposit(&self, account_id: &[u8]) -> Balance {
+        self.database
+            .get_storage_deposit(account_id)
+            .unwrap_or_default()
+    }
+
+    /// Returns the reserved balance of `account_id`.
+    pub fn reserved_balance(&self, account_id: &[u8]) -> Balance {
+        self.database
+            .get_reserved_balance(account_id)
+            .unwrap_or_default()
+    }
+
     /// Sets the balance of `account_id` to `new_balance`.
     pub fn set_balance(&mut self, account_id: Vec<u8>, new_balance: Balance) {
         self.database.set_balance(&account_id, new_balance);
     }
 
     /// Sets the value transferred from the caller to the callee as part of the call.
+    ///
+    /// # Panics
+    ///
+    /// If `value` is non-zero and the caller has not been set or does not have at
+    /// least `value` free balance.
     pub fn set_value_transferred(&mut self, value: Balance) {
+        if value > 0 {
+            let caller = self
+                .exec_context
+                .caller
+                .as_ref()
+                .expect("no caller has been set");
+            let balance = self
+                .database
+                .get_balance(caller.as_bytes())
+                .unwrap_or_default();
+            assert!(
+                balance >= value,
+                "the caller cannot transfer a value of {value}, it has only a free \
+                balance of {balance}"
+            );
+        }
         self.exec_context.value_transferred = value;
     }
 
+    /// Sets up a call transferring `value` from the caller to the callee.
+    ///
+    /// Sets the value transferred and moves `value` from the balance of the caller
+    /// to the balance of the callee, so that both are consistent.
+    ///
+    /// # Panics
+    ///
+    /// If the caller or callee have not been set, the caller does not have at
+    /// least `value` free balance or the balance of the callee would overflow.
+    pub fn transfer_in(&mut self, value: Balance) {
+        self.set_value_transferred(value);
+        let caller = self
+            .exec_context
+            .caller
+            .as_ref()
+            .expect("no caller has been set")
+            .as_bytes()
+            .to_vec();
+        let callee = self.get_callee();
+
+        let caller_old_balance = self.database.get_balance(&caller).unwrap_or_default();
+        let caller_new_balance =
+            caller_old_balance.checked_sub(value).unwrap_or_else(|| {
+                panic!(
+                    "the caller has a balance of {caller_old_balance}, which is too \
+                    low to transfer in {value}"
+                )
+            });
+        let callee_old_balance = self.database.get_balance(&callee).unwrap_or_default();
+        let callee_new_balance =
+            callee_old_balance.checked_add(value).unwrap_or_else(|| {
+                panic!(
+                    "transferring in {value} overflows the callee balance of \
+                    {callee_old_balance}"
+                )
+            });
+        self.database.set_balance(&caller, caller_new_balance);
+        self.database.set_balance(&callee, callee_new_balance);
+    }
+
     /// Set the block timestamp for the execution context.
-    pub fn set_block_timestamp(&mut self, new_block_timestamp: BlockTimestamp) {
+    ///
+    /// Returns an error if `new_block_timestamp` lies before the current block
+    /// timestamp.
+    pub fn set_block_timestamp(
+        &mut self,
+        new_block_timestamp: BlockTimestamp,
+    ) -> Result<(), Error> {
+        if new_block_timestamp < self.exec_context.block_timestamp {
+            return Err(Error::DecreasingBlockTimestamp)
+        }
         self.exec_context.block_timestamp = new_block_timestamp;
+        Ok(())
     }
 
     /// Set the block number for the execution context.
-    pub fn set_block_number(&mut self, new_block_number: BlockNumber) {
+    ///
+    /// Returns an error if `new_block_number` lies before the current block number.
+    pub fn set_block_number(
+        &mut self,
+        new_block_number: BlockNumber,
+    ) -> Result<(), Error> {
+        if new_block_number < self.exec_context.block_number {
+            return Err(Error::DecreasingBlockNumber)
+        }
         self.exec_context.block_number = new_block_number;
+        Ok(())
+    }
+
+    /// Moves the execution context to the block `number` at `timestamp`.
+    ///
+    /// # Panics
+    ///
+    /// If `number` lies before the current block number or `timestamp` lies before
+    /// the current block timestamp.
+    pub fn at_block(
+        &mut self,
+        number: BlockNumber,
+        timestamp: BlockTimestamp,
+    ) -> &mut Self {
+        assert!(
+            number >= self.exec_context.block_number,
+            "cannot move back from block {} to block {number}",
+            self.exec_context.block_number,
+        );
+        assert!(
+            timestamp >= self.exec_context.block_timestamp,
+            "cannot move back from timestamp {} to timestamp {timestamp}",
+            self.exec_context.block_timestamp,
+        );
+        self.exec_context.block_number = number;
+        self.exec_context.block_timestamp = timestamp;
+        self
+    }
+
+    /// Sets the caller of the execution context, see [`Engine::set_caller`].
+    pub fn with_caller(&mut self, caller: Vec<u8>) -> &mut Self {
+        self.set_caller(caller);
+        self
+    }
+
+    /// Sets the value transferred to the callee, see
+    /// [`Engine::set_value_transferred`].
+    ///
+    /// # Panics
+    ///
+    /// If `value` is non-zero and the caller has not been set or does not have at
+    /// least `value` free balance.
+    pub fn with_value(&mut self, value: Balance) -> &mut Self {
+        self.set_value_transferred(value);
+        self
+    }
+
+    /// Registers a stub for the chain extension method `func_id` which ignores its
+    /// input and returns the status code and output produced by `f`.
+    ///
+    /// Since `f` may hold state, consecutive calls can return different values.
+    pub fn set_chain_extension_return<O, F>(&mut self, func_id: u32, mut f: F)
+    where
+        O: scale::Encode + 'static,
+        F: FnMut() -> (u32, O) + 'static,
+    {
+        self.chain_extension_handler
+            .register_typed(func_id, move |()| f());
+    }
+
+    /// Sets the price of a unit of gas, see `weight_to_fee`.
+    pub fn set_gas_price(&mut self, gas_price: Balance) {
+        self.chain_spec.gas_price = gas_price;
+    }
+
+    /// Sets the time by which the block timestamp advances per block.
+    ///
+    /// A block time of `0` makes all blocks share the same timestamp.
+    pub fn set_block_time(&mut self, block_time: BlockTimestamp) {
+        self.chain_spec.block_time = block_time;
+    }
+
+    /// Sets the minimum balance of the chain (i.e. the chain's existential deposit).
+    ///
+    /// Transfers must neither leave the sender nor endow the receiver with less
+    /// than the minimum balance. A minimum balance of `0` disables the existential
+    /// deposit.
+    pub fn set_minimum_balance(&mut self, minimum_balance: Balance) {
+        self.chain_spec.minimum_balance = minimum_balance;
+    }
+
+    /// Enables or disables the debug logging of contracts.
+    ///
+    /// While disabled, `debug_message` fails with `LoggingDisabled` and neither
+    /// records nor prints the message.
+    pub fn set_debug_logging(&mut self, enabled: bool) {
+        self.chain_spec.debug_logging_enabled = enabled;
+    }
+
+    /// Sets the amount of gas available to a contract execution.
+    pub fn set_gas_limit(&mut self, gas_limit: u64) {
+        self.chain_spec.gas_limit = gas_limit;
+    }
+
+    /// Sets the seed from which the output of `random` is derived.
+    pub fn set_random_seed(&mut self, seed: [u8; 32]) {
+        self.chain_spec.random_seed = seed;
+    }
+
+    /// Sets the maximum number of contract frames which may be active at once.
+    pub fn set_max_call_depth(&mut self, max_call_depth: u32) {
+        self.chain_spec.max_call_depth = max_call_depth;
+    }
+
+    /// Returns the number of currently active contract frames.
+    ///
+    /// This is `0` as long as no cross-contract call is in progress.
+    pub fn call_depth(&self) -> u32 {
+        self.contracts.call_depth()
+    }
+
+    /// Returns the frames of the active contract executions, the outermost frame
+    /// first and the currently executing frame last.
+    pub fn call_stack(&self) -> Vec<Frame> {
+        self.suspended_contexts
+            .iter()
+            .map(Frame::from)
+            .chain(core::iter::once(Frame::from(&self.exec_context)))
+            .collect()
+    }
+
+    /// Returns the amount of gas consumed by the current contract execution.
+    pub fn gas_consumed(&self) -> u64 {
+        self.exec_context.gas_consumed
+    }
+
+    /// Executes `entry_point` of a contract at the callee with `input`.
+    ///
+    /// The contract is executed like a call from the caller without transferring
+    /// value. Its storage changes are reverted if it reverts or traps.
+    ///
+    /// Returns the flags and output the contract returned with, or the reason of
+    /// the contract trapping. A contract which terminated itself successfully has
+    /// returned.
+    pub fn execute_entry_point(
+        &mut self,
+        entry_point: fn(&mut Engine),
+        input: Vec<u8>,
+    ) -> Result<ExecReturn, TrapReason> {
+        let callee = self.get_callee();
+        self.execute_at(&callee, entry_point, input)
+    }
+
+    /// Executes the constructor of the contract registered at `account` without
+    /// input, like [`Engine::execute_entry_point`].
+    ///
+    /// # Panics
+    ///
+    /// If no contract is registered at `account`.
+    pub fn run_deploy(&mut self, account: &[u8]) -> Result<ExecReturn, TrapReason> {
+        let contract = self.registered_contract(account);
+        self.execute_at(account, contract.deploy, Vec::new())
+    }
+
+    /// Executes a message of the contract registered at `account` with `input`,
+    /// like [`Engine::execute_entry_point`].
+    ///
+    /// # Panics
+    ///
+    /// If no contract is registered at `account`.
+    pub fn run_call(
+        &mut self,
+        account: &[u8],
+        input: Vec<u8>,
+    ) -> Result<ExecReturn, TrapReason> {
+        let contract = self.registered_contract(account);
+        self.execute_at(account, contract.call, input)
+    }
+
+    /// Executes the constructor with `selector` of the contract registered at
+    /// `account`, passing the SCALE encoded `args`.
+    ///
+    /// Returns the output of the constructor decoded as `R`, whether or not the
+    /// constructor reverted. Use [`Engine::run_call`] to inspect the return flags.
+    ///
+    /// # Panics
+    ///
+    /// If no contract is registered at `account` or if the output does not decode
+    /// as `R`.
+    pub fn invoke_constructor<A, R>(
+        &mut self,
+        account: &[u8],
+        selector: [u8; 4],
+        args: &A,
+    ) -> Result<R, TrapReason>
+    where
+        A: scale::Encode,
+        R: scale::Decode,
+    {
+        let contract = self.registered_contract(account);
+        self.invoke(account, contract.deploy, selector, args)
+    }
+
+    /// Executes the message with `selector` of the contract registered at
+    /// `account`, passing the SCALE encoded `args`.
+    ///
+    /// Returns the output of the message decoded as `R`, whether or not the
+    /// message reverted. Use [`Engine::run_call`] to inspect the return flags.
+    ///
+    /// # Panics
+    ///
+    /// If no contract is registered at `account` or if the output does not decode
+    /// as `R`.
+    pub fn invoke_message<A, R>(
+        &mut self,
+        account: &[u8],
+        selector: [u8; 4],
+        args: &A,
+    ) -> Result<R, TrapReason>
+    where
+        A: scale::Encode,
+        R: scale::Decode,
+    {
+        let contract = self.registered_contract(account);
+        self.invoke(account, contract.call, selector, args)
+    }
+
+    /// Executes `entry_point` of the contract at `account` with the input made up
+    /// of `selector` and the encoded `args`, and decodes its output.
+    fn invoke<A, R>(
+        &mut self,
+        account: &[u8],
+        entry_point: fn(&mut Engine),
+        selector: [u8; 4],
+        args: &A,
+    ) -> Result<R, TrapReason>
+    where
+        A: scale::Encode,
+        R: scale::Decode,
+    {
+        let mut input = selector.to_vec();
+        args.encode_to(&mut input);
+        let exec_return = self.execute_at(account, entry_point, input)?;
+        let output = <R as scale::Decode>::decode(&mut &exec_return.data[..])
+            .unwrap_or_else(|err| {
+                panic!(
+                    "the output of selector {selector:?} does not decode as `{}`: {err}",
+                    core::any::type_name::<R>()
+                )
+            });
+        Ok(output)
+    }
+
+    /// Returns the contract registered at `account`.
+    fn registered_contract(&self, account: &[u8]) -> Contract {
+        self.database
+            .get_code_hash(account)
+            .and_then(|code_hash| self.contracts.get(&code_hash))
+            .unwrap_or_else(|| panic!("no contract is registered at {account:?}"))
+    }
+
+    /// Executes `entry_point` of the contract at `callee` with `input`, see
+    /// [`Engine::execute_entry_point`].
+    fn execute_at(
+        &mut self,
+        callee: &[u8],
+        entry_point: fn(&mut Engine),
+        input: Vec<u8>,
+    ) -> Result<ExecReturn, TrapReason> {
+        let mut exec_context = self.nested_context(callee, 0, input);
+        exec_context.caller = self.exec_context.caller.clone();
+        let snapshot = self.database.snapshot();
+        self.push_context(exec_context);
+        let result = catch_unwind(AssertUnwindSafe(|| entry_point(self)));
+        let exec_context = self
+            .pop_context()
+            .expect("the context of the execution must have been pushed");
+        self.exec_context.gas_consumed = exec_context.gas_consumed;
+        let exec_return = ExecReturn {
+            flags: exec_context.return_flags,
+            data: exec_context.output,
+        };
+        let result = match result {
+            Ok(()) => Ok(exec_return),
+            // A contract which terminated itself has finished successfully.
+            Err(payload) if is_successful_termination(&*payload) => Ok(exec_return),
+            Err(payload) => Err(trap_reason(&*payload)),
+        };
+        match &result {
+            Ok(exec_return) if !exec_return.did_revert() => {
+                self.database.release(snapshot)
+            }
+            _ => self.database.restore(snapshot),
+        }
+        result
+    }
+
+    /// Pushes the execution context of `caller` calling `callee` with `value`
+    /// transferred.
+    ///
+    /// The context is popped again once the returned guard is dropped, which
+    /// restores the previously executing context.
+    pub fn push_frame(
+        &mut self,
+        caller: Vec<u8>,
+        callee: Vec<u8>,
+        value: Balance,
+    ) -> FrameGuard<'_> {
+        let mut exec_context = self.nested_context(&callee, value, Vec::new());
+        exec_context.caller = Some(caller.into());
+        self.push_context(exec_context);
+        FrameGuard { engine: self }
+    }
+}
+
+/// Guard for an execution context pushed by [`Engine::push_frame`].
+///
+/// Dereferences to the engine and pops the pushed context on drop.
+pub struct FrameGuard<'a> {
+    engine: &'a mut Engine,
+}
+
+impl core::ops::Deref for FrameGuard<'_> {
+    type Target = Engine;
+
+    fn deref(&self) -> &Self::Target {
+        self.engine
+    }
+}
+
+impl core::ops::DerefMut for FrameGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.engine
+    }
+}
+
+impl Drop for FrameGuard<'_> {
+    fn drop(&mut self) {
+        self.engine.pop_context();
     }
 }
 
@@ -293,6 +1246,33 @@ mod tests {
         assert_eq!(engine.get_callee(), account_id);
     }
 
+    #[test]
+    fn dropping_frame_guard_restores_context() {
+        // given
+        let mut engine = Engine::new();
+        engine.set_caller(vec![1; 32]);
+        engine.set_callee(vec![2; 32]);
+
+        // when
+        {
+            let mut frame = engine.push_frame(vec![2; 32], vec![3; 32], 10);
+
+            // then
+            assert_eq!(frame.get_callee(), vec![3; 32]);
+            let mut value = [0_u8; 16];
+            frame.value_transferred(&mut &mut value[..]);
+            assert_eq!(u128::from_le_bytes(value), 10);
+            assert_eq!(frame.call_stack().len(), 2);
+        }
+
+        // then
+        let outer = engine.call_stack();
+        assert_eq!(outer.len(), 1);
+        assert_eq!(outer[0].caller, Some(AccountId::from_bytes(&[1; 32])));
+        assert_eq!(outer[0].callee, Some(AccountId::from_bytes(&[2; 32])));
+        assert_eq!(outer[0].value_transferred, 0);
+    }
+
     #[test]
     fn count_cells_per_account_must_stay_the_same() {
         // given
@@ -300,12 +1280,16 @@ mod tests {
         let account_id = vec![1; 32];
         engine.set_callee(account_id.clone());
         let key: &[u8; 32] = &[0x42; 32];
-        engine.set_storage(key, &[0x05_u8; 5]);
+        engine
+            .set_storage(key, &[0x05_u8; 5])
+            .expect("storing must succeed");
         assert_eq!(engine.count_used_storage_cells(&account_id), Ok(1));
 
         // when
         // we set the storage a second time
-        engine.set_storage(key, &[0x05_u8; 6]);
+        engine
+            .set_storage(key, &[0x05_u8; 6])
+            .expect("storing must succeed");
 
         // then
         // the amount of storage cells used must have stayed the same
@@ -319,7 +1303,9 @@ mod tests {
         let account_id = vec![1; 32];
         engine.set_callee(account_id.clone());
         let key: &[u8; 32] = &[0x42; 32];
-        engine.set_storage(key, &[0x05_u8; 5]);
+        engine
+            .set_storage(key, &[0x05_u8; 5])
+            .expect("storing must succeed");
         assert_eq!(engine.count_used_storage_cells(&account_id), Ok(1));
 
         // when
@@ -338,16 +1324,47 @@ mod tests {
 
         // when
         engine.set_callee(vec![1; 32]);
-        engine.set_storage(key, &[0x05_u8; 5]);
-        engine.set_storage(key, &[0x05_u8; 6]);
+        engine
+            .set_storage(key, &[0x05_u8; 5])
+            .expect("storing must succeed");
+        engine
+            .set_storage(key, &[0x05_u8; 6])
+            .expect("storing must succeed");
         engine.get_storage(key, &mut &mut buf[..]).unwrap();
 
         engine.set_callee(vec![2; 32]);
-        engine.set_storage(key, &[0x07_u8; 7]);
+        engine
+            .set_storage(key, &[0x07_u8; 7])
+            .expect("storing must succeed");
         engine.get_storage(key, &mut &mut buf[..]).unwrap();
 
         // then
         assert_eq!(engine.count_writes(), 3);
         assert_eq!(engine.count_reads(), 2);
     }
+
+    #[test]
+    fn iter_contract_storage_reflects_taken_storage() {
+        // given
+        let mut engine = Engine::new();
+        let account_id = vec![1; 32];
+        engine.set_callee(account_id.clone());
+        engine
+            .set_storage(&[2], &[0x02])
+            .expect("storing must succeed");
+        engine
+            .set_storage(&[1], &[0x01])
+            .expect("storing must succeed");
+        engine
+            .set_storage(&[3], &[0x03])
+            .expect("storing must succeed");
+
+        // when
+        let mut buf = [0_u8; 32];
+        engine.take_storage(&[2], &mut &mut buf[..]).unwrap();
+
+        // then
+        let entries: Vec<_> = engine.iter_contract_storage(&account_id).collect();
+        assert_eq!(entries, vec![(vec![1], vec![0x01]), (vec![3], vec![0x03])]);
+    }
 }
@@ -12,9 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::ext::{
-    Engine,
-    Error,
+use crate::{
+    ext::{
+        CallFlags,
+        ChainSpec,
+        Engine,
+        Error,
+        ExecReturn,
+        GasModel,
+        TerminationResult,
+        TopicHasher,
+        TrapReason,
+    },
+    test_api::topic_for,
+    types::{
+        AccountId,
+        Weight,
+    },
+    AddressDeriver,
+    ChainExtension,
+    ChainExtensionObject,
+    Extension,
 };
 use secp256k1::{
     ecdsa::RecoverableSignature,
@@ -44,7 +62,9 @@ fn store_load_clear() {
     let res = engine.get_storage(key, output);
     assert_eq!(res, Err(Error::KeyNotFound));
 
-    engine.set_storage(key, &[0x05_u8; 5]);
+    engine
+        .set_storage(key, &[0x05_u8; 5])
+        .expect("storing must succeed");
     let res = engine.get_storage(key, output);
     assert_eq!(res, Ok(()),);
     assert_eq!(output[..5], [0x05; 5]);
@@ -54,6 +74,51 @@ fn store_load_clear() {
     assert_eq!(res, Err(Error::KeyNotFound));
 }
 
+#[test]
+fn set_storage_is_bounded_by_the_max_storage_bytes() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    engine.chain_spec.max_storage_bytes = 10;
+    engine
+        .set_storage(&[1], &[0; 6])
+        .expect("storing must succeed");
+
+    // when
+    let up_to_the_limit = engine.set_storage(&[2], &[0; 4]);
+    let past_the_limit = engine.set_storage(&[3], &[0; 1]);
+
+    // then
+    assert_eq!(up_to_the_limit, Ok(None));
+    assert_eq!(past_the_limit, Err(Error::StorageExhausted));
+    assert_eq!(engine.contains_storage(&[3]), None);
+    // overwriting a value only counts the difference in length
+    assert_eq!(engine.set_storage(&[1], &[1; 6]), Ok(Some(6)));
+    assert_eq!(
+        engine.set_storage(&[1], &[1; 7]),
+        Err(Error::StorageExhausted)
+    );
+    assert_eq!(engine.clear_storage(&[2]), Some(4));
+    assert_eq!(engine.set_storage(&[3], &[0; 4]), Ok(None));
+}
+
+#[test]
+fn set_storage_returning_delta_reports_the_change_in_length() {
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let key: &[u8; 32] = &[0x42; 32];
+    let mut set = |value: &[u8]| {
+        engine
+            .set_storage_returning_delta(key, value)
+            .expect("storing must succeed")
+    };
+
+    assert_eq!(set(&[0x05; 5]), 5);
+    assert_eq!(set(&[0x05; 8]), 3);
+    assert_eq!(set(&[0x05; 2]), -6);
+    assert_eq!(set(&[0x06; 2]), 0);
+}
+
 #[test]
 fn setting_getting_balance() {
     // given
@@ -88,6 +153,39 @@ fn setting_getting_caller() {
     assert_eq!(&output[..account_id.len()], &account_id);
 }
 
+#[test]
+fn typed_caller_and_callee_accessors() {
+    // given
+    let mut engine = Engine::new();
+    assert_eq!(engine.caller_account(), None);
+
+    // when
+    engine.set_caller(vec![1; 32]);
+    engine.set_callee(vec![2; 32]);
+
+    // then
+    assert_eq!(
+        engine.caller_account(),
+        Some(AccountId::from_bytes(&[1; 32]))
+    );
+    assert_eq!(engine.callee(), AccountId::from_bytes(&[2; 32]));
+}
+
+#[test]
+fn setting_getting_input() {
+    // given
+    let mut engine = Engine::new();
+    let input = [0xCA, 0xFE, 0xBA, 0xBE, 1, 2];
+
+    // when
+    engine.set_input(&input);
+
+    // then
+    let mut output = get_buffer();
+    engine.input(&mut &mut output[..]);
+    assert_eq!(&output[..input.len()], &input);
+}
+
 #[test]
 fn address() {
     // given
@@ -110,34 +208,271 @@ fn transfer() {
     let alice = vec![1; 32];
     let bob = vec![2; 32];
     engine.set_callee(alice.clone());
-    engine.set_balance(alice.clone(), 1337);
+    engine.set_balance(alice.clone(), 2_337_000);
 
     // when
-    let val = scale::Encode::encode(&337u128);
+    let val = scale::Encode::encode(&1_337_000u128);
     assert_eq!(engine.transfer(&bob, &val), Ok(()));
 
     // then
-    assert_eq!(engine.get_balance(alice), Ok(1000));
-    assert_eq!(engine.get_balance(bob), Ok(337));
+    assert_eq!(engine.get_balance(alice), Ok(1_000_000));
+    assert_eq!(engine.get_balance(bob), Ok(1_337_000));
+}
+
+#[test]
+fn transfer_fails_without_sufficient_balance() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let bob = vec![2; 32];
+    engine.set_callee(alice.clone());
+    engine.set_balance(alice.clone(), 2_000_000);
+
+    // when
+    let val = scale::Encode::encode(&2_000_001u128);
+
+    // then
+    assert_eq!(engine.transfer(&bob, &val), Err(Error::TransferFailed));
+    assert_eq!(engine.get_balance(alice), Ok(2_000_000));
+    assert!(engine.get_balance(bob).is_err());
+}
+
+#[test]
+fn transfer_fails_if_the_destination_balance_overflows() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let bob = vec![2; 32];
+    engine.set_callee(alice.clone());
+    engine.set_balance(alice.clone(), 2_000_000);
+    engine.set_balance(bob.clone(), u128::MAX);
+
+    // when
+    let val = scale::Encode::encode(&1u128);
+
+    // then
+    assert_eq!(engine.transfer(&bob, &val), Err(Error::TransferFailed));
+    assert_eq!(engine.get_balance(alice), Ok(2_000_000));
+    assert_eq!(engine.get_balance(bob.clone()), Ok(u128::MAX));
+    let zero = scale::Encode::encode(&0u128);
+    assert_eq!(engine.transfer(&bob, &zero), Ok(()));
+}
+
+#[test]
+fn dry_run_reverts_a_transfer() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let bob = vec![2; 32];
+    engine.set_callee(alice.clone());
+    engine.set_balance(alice.clone(), 2_337_000);
+
+    // when
+    let projected = engine.dry_run(|engine| {
+        let val = scale::Encode::encode(&1_337_000u128);
+        assert_eq!(engine.transfer(&bob, &val), Ok(()));
+        engine.get_balance(bob.clone())
+    });
+
+    // then
+    assert_eq!(projected, Ok(1_337_000));
+    assert_eq!(engine.get_balance(alice), Ok(2_337_000));
+    assert!(engine.get_balance(bob).is_err());
+}
+
+#[test]
+fn dry_run_reverts_events_and_debug_info() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let no_topics = scale::Encode::encode(&scale::Compact(0u32));
+
+    // when
+    let events_inside = engine.dry_run(|engine| {
+        engine.deposit_event(&no_topics, &[42]);
+        assert_eq!(engine.debug_message("dry run"), Ok(()));
+        engine
+            .set_storage(&[0x42; 32], &[1])
+            .expect("storing must succeed");
+        engine.emitted_events().len()
+    });
+
+    // then
+    assert_eq!(events_inside, 1);
+    assert_eq!(engine.get_emitted_events().count(), 0);
+    assert_eq!(engine.get_emitted_debug_messages().into_iter().count(), 0);
+    assert_eq!(engine.count_writes(), 0);
+    assert_eq!(engine.host_fn_calls("deposit_event"), 0);
+}
+
+/// Returns the existential deposit configured for `engine`.
+fn minimum_balance(engine: &mut Engine) -> u128 {
+    let mut output = get_buffer();
+    engine.minimum_balance(&mut &mut output[..]);
+    scale::Decode::decode(&mut &output[..16]).expect("decoding failed")
+}
+
+#[test]
+fn transfer_must_not_leave_sender_with_dust() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let bob = vec![2; 32];
+    let ed = minimum_balance(&mut engine);
+    engine.set_callee(alice.clone());
+    engine.set_balance(alice.clone(), 3 * ed);
+    engine.set_balance(bob.clone(), ed);
+
+    // when
+    let dust = scale::Encode::encode(&(2 * ed + 1));
+    let exact = scale::Encode::encode(&(2 * ed));
+    let all = scale::Encode::encode(&ed);
+
+    // then
+    assert_eq!(engine.transfer(&bob, &dust), Err(Error::TransferFailed));
+    assert_eq!(engine.get_balance(alice.clone()), Ok(3 * ed));
+    assert_eq!(engine.transfer(&bob, &exact), Ok(()));
+    assert_eq!(engine.get_balance(alice.clone()), Ok(ed));
+    // the sender may be reaped entirely
+    assert_eq!(engine.transfer(&bob, &all), Ok(()));
+    assert_eq!(engine.get_balance(alice), Ok(0));
+}
+
+#[test]
+fn zero_minimum_balance_disables_existential_deposit() {
+    // given
+    let mut engine = Engine::new();
+    assert_eq!(minimum_balance(&mut engine), 1_000_000);
+    engine.set_callee(CALLER.to_vec());
+    engine.set_balance(CALLER.to_vec(), 10);
+
+    // when
+    engine.set_minimum_balance(0);
+
+    // then
+    assert_eq!(minimum_balance(&mut engine), 0);
+    assert_eq!(
+        engine.transfer(&CALLEE, &scale::Encode::encode(&1u128)),
+        Ok(())
+    );
+    assert_eq!(engine.get_balance(CALLER.to_vec()), Ok(9));
+    assert_eq!(engine.get_balance(CALLEE.to_vec()), Ok(1));
+}
+
+#[test]
+fn accounts_exist_above_the_minimum_balance_or_as_contracts() {
+    // given
+    let mut engine = Engine::new();
+    let dust = vec![1; 32];
+    let endowed = vec![2; 32];
+    let contract = vec![3; 32];
+
+    // when
+    engine.set_balance(dust.clone(), 999_999);
+    engine.set_balance(endowed.clone(), 1_000_000);
+    engine.set_contract(contract.clone());
+
+    // then
+    assert!(!engine.account_exists(&dust));
+    assert!(engine.account_exists(&endowed));
+    assert!(engine.account_exists(&contract));
+    assert!(!engine.account_exists(&[4; 32]));
+}
+
+#[test]
+fn transfer_must_endow_new_account_with_minimum_balance() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let bob = vec![2; 32];
+    let ed = minimum_balance(&mut engine);
+    engine.set_callee(alice.clone());
+    engine.set_balance(alice.clone(), 3 * ed);
+
+    // when
+    let dust = scale::Encode::encode(&(ed - 1));
+    let exact = scale::Encode::encode(&ed);
+
+    // then
+    assert_eq!(engine.transfer(&bob, &dust), Err(Error::TransferFailed));
+    assert!(engine.get_balance(bob.clone()).is_err());
+    assert_eq!(engine.transfer(&bob, &exact), Ok(()));
+    assert_eq!(engine.get_balance(bob.clone()), Ok(ed));
+    // once the account exists it may receive any amount
+    assert_eq!(engine.transfer(&bob, &dust), Ok(()));
+    assert_eq!(engine.get_balance(bob), Ok(2 * ed - 1));
 }
 
 #[test]
 fn debug_messages() {
     let mut engine = Engine::new();
-    engine.debug_message("foobar");
+    assert_eq!(engine.debug_message("foobar"), Ok(()));
     let mut recorded = engine.get_emitted_debug_messages().into_iter();
     assert_eq!(recorded.next(), Some("foobar".into()));
     assert_eq!(recorded.next(), None);
 }
 
+#[test]
+fn debug_messages_beyond_the_debug_buffer_are_dropped() {
+    // given
+    let mut engine = Engine::new();
+    engine.chain_spec.max_debug_buffer_len = 10;
+
+    // when
+    let results =
+        ["foobar", "bazbaz", "quux"].map(|message| engine.debug_message(message));
+
+    // then
+    assert_eq!(results, [Ok(()), Ok(()), Ok(())]);
+    let recorded: Vec<_> = engine.get_emitted_debug_messages().into_iter().collect();
+    assert_eq!(recorded, vec!["foobar".to_string(), "quux".to_string()]);
+    assert!(engine.debug_truncated());
+}
+
+#[test]
+fn debug_messages_are_not_recorded_while_logging_is_disabled() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_debug_logging(false);
+
+    // when
+    let res = engine.debug_message("foobar");
+
+    // then
+    assert_eq!(res, Err(Error::LoggingDisabled));
+    assert_eq!(engine.get_emitted_debug_messages().into_iter().count(), 0);
+
+    // when
+    engine.set_debug_logging(true);
+
+    // then
+    assert_eq!(engine.debug_message("foobar"), Ok(()));
+    assert_eq!(engine.get_emitted_debug_messages().into_iter().count(), 1);
+}
+
+#[test]
+fn debug_messages_without_debug_buffer_fail() {
+    // given
+    let mut engine = Engine::new();
+    engine.chain_spec.max_debug_buffer_len = 0;
+
+    // when
+    let res = engine.debug_message("foobar");
+
+    // then
+    assert_eq!(res, Err(Error::LoggingDisabled));
+    assert_eq!(engine.get_emitted_debug_messages().into_iter().count(), 0);
+    assert!(!engine.debug_truncated());
+}
+
 #[test]
 fn events() {
     // given
     let mut engine = Engine::new();
     let topics_count: scale::Compact<u32> = scale::Compact(2u32);
     let mut enc_topics_count = scale::Encode::encode(&topics_count);
-    let topic1 = vec![12u8, 13];
-    let topic2 = vec![14u8, 15];
+    let topic1 = vec![12u8; 32];
+    let topic2 = vec![14u8; 32];
     let data = &vec![21, 22, 23];
 
     // when
@@ -164,113 +499,549 @@ fn events() {
 }
 
 #[test]
-fn value_transferred() {
+fn emitted_events_can_be_read_and_cleared() {
     // given
     let mut engine = Engine::new();
-    let value = 1337;
-    engine.set_value_transferred(value);
+    let topics = scale::Encode::encode(&scale::Compact(0u32));
+    engine.deposit_event(&topics, &[1]);
+    engine.deposit_event(&topics, &[2]);
 
     // when
-    let output = &mut &mut get_buffer()[..];
-    engine.value_transferred(output);
+    let events = engine.emitted_events();
+    engine.clear_events();
 
     // then
-    let output = <u128 as scale::Decode>::decode(&mut &output[..16])
-        .expect("decoding value transferred failed");
-    assert_eq!(output, value);
+    let data: Vec<_> = events.into_iter().map(|event| event.data).collect();
+    assert_eq!(data, vec![vec![1], vec![2]]);
+    assert!(engine.emitted_events().is_empty());
 }
 
 #[test]
-#[should_panic(
-    expected = "the output buffer is too small! the decoded storage is of size 16 bytes, but the output buffer has only room for 8."
-)]
-fn must_panic_when_buffer_too_small() {
+fn events_can_be_taken_by_predicate() {
     // given
     let mut engine = Engine::new();
-    engine.set_callee(vec![1; 32]);
-    let key: &[u8; 32] = &[0x42; 32];
-    engine.set_storage(key, &[0x05_u8; 16]);
+    let topics = scale::Encode::encode(&scale::Compact(0u32));
+    engine.deposit_event(&topics, &[1]);
+    engine.deposit_event(&topics, &[2]);
+    engine.deposit_event(&topics, &[3]);
 
     // when
-    let mut small_buffer = [0; 8];
-    let output = &mut &mut small_buffer[..];
-    let _ = engine.get_storage(key, output);
+    let taken = engine.take_events_matching(|event| event.data == [2]);
 
     // then
-    unreachable!("`get_storage` must already have panicked");
+    assert_eq!(taken.len(), 1);
+    assert_eq!(taken[0].data, vec![2]);
+    let remaining: Vec<_> = engine
+        .emitted_events()
+        .into_iter()
+        .map(|event| event.data)
+        .collect();
+    assert_eq!(remaining, vec![vec![1], vec![3]]);
 }
 
 #[test]
-fn ecdsa_recovery_test_from_contracts_pallet() {
-    // given
+#[should_panic(
+    expected = "the topics buffer of 65 bytes cannot be split into 2 topics of equal size"
+)]
+fn deposit_event_rejects_unevenly_sized_topics() {
     let mut engine = Engine::new();
-    #[rustfmt::skip]
-    let signature: [u8; 65] = [
-        161, 234, 203,  74, 147, 96,  51, 212,   5, 174, 231,   9, 142,  48, 137, 201,
-        162, 118, 192,  67, 239, 16,  71, 216, 125,  86, 167, 139,  70,   7,  86, 241,
-         33,  87, 154, 251,  81, 29, 160,   4, 176, 239,  88, 211, 244, 232, 232,  52,
-        211, 234, 100, 115, 230, 47,  80,  44, 152, 166,  62,  50,   8,  13,  86, 175,
-         28,
-    ];
-    #[rustfmt::skip]
-    let message_hash: [u8; 32] = [
-        162, 28, 244, 179, 96, 76, 244, 178, 188,  83, 230, 248, 143, 106,  77, 117,
-        239, 95, 244, 171, 65, 95,  62, 153, 174, 166, 182,  28, 130,  73, 196, 208
-    ];
-
-    // when
-    let mut output = [0; 33];
-    engine
-        .ecdsa_recover(&signature, &message_hash, &mut output)
-        .expect("must work");
+    let mut topics = scale::Encode::encode(&scale::Compact(2u32));
+    topics.extend_from_slice(&[1; 65]);
+    engine.deposit_event(&topics, &[]);
+}
 
-    // then
-    #[rustfmt::skip]
-    const EXPECTED_COMPRESSED_PUBLIC_KEY: [u8; 33] = [
-          2, 121, 190, 102, 126, 249, 220, 187, 172, 85, 160,  98, 149, 206, 135, 11,
-          7,   2, 155, 252, 219,  45, 206,  40, 217, 89, 242, 129,  91,  22, 248, 23,
-        152,
-    ];
-    assert_eq!(output, EXPECTED_COMPRESSED_PUBLIC_KEY);
+#[test]
+#[should_panic(expected = "every topic must be 32 bytes, but the topics are 16 bytes")]
+fn deposit_event_rejects_topics_of_wrong_size() {
+    let mut engine = Engine::new();
+    let mut topics = scale::Encode::encode(&scale::Compact(2u32));
+    topics.extend_from_slice(&[1; 32]);
+    engine.deposit_event(&topics, &[]);
 }
 
 #[test]
-fn ecdsa_recovery_with_secp256k1_crate() {
-    // given
+#[should_panic(expected = "the event has 5 topics, but at most 4 are allowed")]
+fn deposit_event_rejects_too_many_topics() {
     let mut engine = Engine::new();
-    let seckey = [
-        59, 148, 11, 85, 134, 130, 61, 253, 2, 174, 59, 70, 27, 180, 51, 107, 94, 203,
-        174, 253, 102, 39, 170, 146, 46, 252, 4, 143, 236, 12, 136, 28,
-    ];
-    let pubkey = PublicKey::from_slice(&[
-        2, 29, 21, 35, 7, 198, 183, 43, 14, 208, 65, 139, 14, 112, 205, 128, 231, 245,
-        41, 91, 141, 134, 245, 114, 45, 63, 82, 19, 251, 210, 57, 79, 54,
-    ])
-    .expect("pubkey creation failed");
+    let mut topics = scale::Encode::encode(&scale::Compact(5u32));
+    topics.extend_from_slice(&[1; 5 * 32]);
+    engine.deposit_event(&topics, &[]);
+}
 
-    let mut msg_hash = [0; 32];
-    crate::hashing::sha2_256(b"Some message", &mut msg_hash);
+#[test]
+#[should_panic(
+    expected = "the topics buffer is empty, but it must start with the number of topics"
+)]
+fn deposit_event_rejects_empty_topics_buffer() {
+    let mut engine = Engine::new();
+    engine.deposit_event(&[], &[]);
+}
 
-    let msg = Message::from_slice(&msg_hash).expect("message creation failed");
-    let seckey = SecretKey::from_slice(&seckey).expect("secret key creation failed");
-    let recoverable_signature: RecoverableSignature =
-        SECP256K1.sign_ecdsa_recoverable(&msg, &seckey);
+#[test]
+#[should_panic(
+    expected = "the event has no topics, but the topics buffer contains 32 trailing bytes"
+)]
+fn deposit_event_rejects_trailing_bytes_without_topics() {
+    let mut engine = Engine::new();
+    let mut topics = scale::Encode::encode(&scale::Compact(0u32));
+    topics.extend_from_slice(&[1; 32]);
+    engine.deposit_event(&topics, &[]);
+}
 
-    let recovery_id = recoverable_signature.serialize_compact().0.to_i32() as u8;
-    let mut signature = recoverable_signature.serialize_compact().1.to_vec();
-    signature.push(recovery_id);
-    let signature_with_recovery_id: [u8; 65] = signature
-        .try_into()
-        .expect("unable to create signature with recovery id");
+#[test]
+fn events_record_their_index_and_block_number() {
+    // given
+    let mut engine = Engine::new();
+    let no_topics = scale::Encode::encode(&scale::Compact(0u32));
 
     // when
-    let mut output = [0; 33];
-    engine
-        .ecdsa_recover(&signature_with_recovery_id, msg.as_ref(), &mut output)
-        .expect("ecdsa recovery failed");
+    engine.deposit_event(&no_topics, &[1]);
+    engine.deposit_event(&no_topics, &[2]);
+    engine.advance_block();
+    engine.deposit_event(&no_topics, &[3]);
 
     // then
-    assert_eq!(output, pubkey.serialize());
+    let recorded = |engine: &Engine| {
+        engine
+            .emitted_events()
+            .iter()
+            .map(|event| (event.index, event.block_number, event.data[0]))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(recorded(&engine), vec![(0, 0, 1), (1, 0, 2), (2, 1, 3)]);
+    // indices keep increasing after clearing the recorded events
+    engine.clear_events();
+    engine.deposit_event(&no_topics, &[4]);
+    assert_eq!(recorded(&engine), vec![(3, 1, 4)]);
+}
+
+#[test]
+fn events_can_be_queried_by_topic() {
+    // given
+    let mut engine = Engine::new();
+    let enc_topics = |topics: &[[u8; 32]]| {
+        let mut enc = scale::Encode::encode(&scale::Compact(topics.len() as u32));
+        topics.iter().for_each(|topic| enc.extend_from_slice(topic));
+        enc
+    };
+
+    // when
+    engine.deposit_event(
+        &enc_topics(&[[1; 32], [3; 32]]),
+        &scale::Encode::encode(&7u32),
+    );
+    engine.deposit_event(&enc_topics(&[[3; 32]]), &scale::Encode::encode(&8u32));
+    engine.deposit_event(&enc_topics(&[]), &scale::Encode::encode(&9u32));
+
+    // then
+    let decoded = |topic: &[u8]| {
+        engine
+            .events_with_topic(topic)
+            .iter()
+            .map(|event| event.decode_data::<u32>().expect("decoding failed"))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(decoded(&[1; 32]), vec![7]);
+    assert_eq!(decoded(&[3; 32]), vec![7, 8]);
+    assert!(decoded(&[2; 32]).is_empty());
+}
+
+/// An event as emitted by a token contract.
+#[derive(Debug, PartialEq, scale::Encode, scale::Decode)]
+struct Transferred {
+    from: [u8; 32],
+    to: [u8; 32],
+    value: u128,
+}
+
+#[test]
+fn emitted_events_can_be_found_by_type() {
+    // given
+    let mut engine = Engine::new();
+    let event = Transferred {
+        from: CALLER,
+        to: CALLEE,
+        value: 100,
+    };
+    let topics = scale::Encode::encode(&scale::Compact(0u32));
+
+    // when
+    engine.deposit_event(&topics, &scale::Encode::encode(&7u32));
+    engine.deposit_event(&topics, &scale::Encode::encode(&event));
+
+    // then
+    assert_eq!(engine.find_event::<Transferred>(), Some(event));
+    assert_eq!(engine.find_event::<u32>(), Some(7));
+    assert_eq!(engine.find_event::<u64>(), None);
+    engine.assert_event_emitted(&Transferred {
+        from: CALLER,
+        to: CALLEE,
+        value: 100,
+    });
+}
+
+#[test]
+#[should_panic(
+    expected = "expected the event 8 to be emitted, but the 1 emitted events \
+                           decoding as `u32` are [7]"
+)]
+fn assert_event_emitted_fails_for_other_events() {
+    let mut engine = Engine::new();
+    let topics = scale::Encode::encode(&scale::Compact(0u32));
+    engine.deposit_event(&topics, &scale::Encode::encode(&7u32));
+    engine.assert_event_emitted(&8u32);
+}
+
+#[test]
+fn topic_for_matches_recorded_topics() {
+    // given
+    let mut engine = Engine::new();
+    let short_topic = topic_for(&1337u128);
+    let long_topic = topic_for(&[7u8; 40]);
+    let mut topics = scale::Encode::encode(&scale::Compact(2u32));
+    topics.extend_from_slice(&short_topic);
+    topics.extend_from_slice(&long_topic);
+
+    // when
+    engine.deposit_event(&topics, &scale::Encode::encode(&9u32));
+
+    // then
+    let mut expected_short = scale::Encode::encode(&1337u128);
+    expected_short.resize(32, 0);
+    assert_eq!(short_topic, expected_short);
+    let mut expected_long = [0; 32];
+    crate::hashing::blake2b_256(&scale::Encode::encode(&[7u8; 40]), &mut expected_long);
+    assert_eq!(long_topic, expected_long.to_vec());
+    let events = engine.events_with_topic(&topic_for(&[7u8; 40]));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].decode_data::<u32>(), Ok(9));
+}
+
+#[test]
+fn topic_for_uses_the_topic_hasher_of_the_chain_spec() {
+    // given
+    let mut engine = Engine::new();
+    let long_value = [7u8; 40];
+    let encoded = scale::Encode::encode(&long_value);
+    let mut blake2 = [0; 32];
+    crate::hashing::blake2b_256(&encoded, &mut blake2);
+    let mut keccak = [0; 32];
+    crate::hashing::keccak_256(&encoded, &mut keccak);
+
+    // when
+    let default_topic = engine.topic_for(&long_value);
+    engine.chain_spec.topic_hasher = TopicHasher::Keccak256;
+    let keccak_topic = engine.topic_for(&long_value);
+
+    // then
+    assert_eq!(default_topic, blake2.to_vec());
+    assert_eq!(default_topic, topic_for(&long_value));
+    assert_eq!(keccak_topic, keccak.to_vec());
+    assert_ne!(keccak_topic, default_topic);
+    // short encodings are not hashed by either hasher
+    assert_eq!(
+        engine.topic_for(&1337u128),
+        TopicHasher::Blake2x256.topic_for(&1337u128)
+    );
+    let mut topics = scale::Encode::encode(&scale::Compact(1u32));
+    topics.extend_from_slice(&keccak_topic);
+    engine.deposit_event(&topics, &[]);
+    assert_eq!(engine.events_with_topic(&keccak_topic).len(), 1);
+}
+
+#[test]
+fn get_storage_partial_copies_from_offset() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let value: Vec<u8> = (0..10).collect();
+    engine
+        .set_storage(&[42], &value)
+        .expect("storing must succeed");
+
+    // when
+    let mut full = [0; 16];
+    let full_len = engine.get_storage_partial(&[42], 0, &mut &mut full[..]);
+    let mut partial = [0; 4];
+    let partial_len = engine.get_storage_partial(&[42], 3, &mut &mut partial[..]);
+    let mut tail = [0; 4];
+    let tail_len = engine.get_storage_partial(&[42], 8, &mut &mut tail[..]);
+
+    // then
+    assert_eq!(full_len, Ok(10));
+    assert_eq!(&full[..10], &value[..]);
+    assert_eq!(&full[10..], &[0; 6]);
+    assert_eq!(partial_len, Ok(10));
+    assert_eq!(partial, [3, 4, 5, 6]);
+    assert_eq!(tail_len, Ok(10));
+    assert_eq!(tail, [8, 9, 0, 0]);
+}
+
+#[test]
+fn get_storage_partial_beyond_end_copies_nothing() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    engine
+        .set_storage(&[42], &[7; 10])
+        .expect("storing must succeed");
+
+    // when
+    let mut output = [0; 4];
+    let len = engine.get_storage_partial(&[42], 20, &mut &mut output[..]);
+
+    // then
+    assert_eq!(len, Ok(10));
+    assert_eq!(output, [0; 4]);
+    assert_eq!(
+        engine.get_storage_partial(&[43], 0, &mut &mut output[..]),
+        Err(Error::KeyNotFound)
+    );
+}
+
+#[test]
+fn block_context_can_be_set_up_fluently() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_balance(vec![1; 32], 100);
+
+    // when
+    engine
+        .at_block(42, 1337)
+        .with_caller(vec![1; 32])
+        .with_value(10);
+
+    // then
+    let mut output = get_buffer();
+    engine.block_number(&mut &mut output[..]);
+    assert_eq!(scale::Decode::decode(&mut &output[..]), Ok(42u32));
+    engine.block_timestamp(&mut &mut output[..]);
+    assert_eq!(scale::Decode::decode(&mut &output[..]), Ok(1337u64));
+    engine.caller(&mut &mut output[..]);
+    assert_eq!(&output[..32], &[1; 32]);
+    engine.value_transferred(&mut &mut output[..]);
+    assert_eq!(scale::Decode::decode(&mut &output[..]), Ok(10u128));
+}
+
+#[test]
+#[should_panic(expected = "cannot move back from block 42 to block 41")]
+fn at_block_rejects_decreasing_block_number() {
+    let mut engine = Engine::new();
+    engine.at_block(42, 0).at_block(41, 0);
+}
+
+#[test]
+fn value_transferred() {
+    // given
+    let mut engine = Engine::new();
+    let value = 1337;
+    engine.set_caller(vec![1; 32]);
+    engine.set_balance(vec![1; 32], value);
+    engine.set_value_transferred(value);
+
+    // when
+    let output = &mut &mut get_buffer()[..];
+    engine.value_transferred(output);
+
+    // then
+    let output = <u128 as scale::Decode>::decode(&mut &output[..16])
+        .expect("decoding value transferred failed");
+    assert_eq!(output, value);
+}
+
+#[test]
+#[should_panic(
+    expected = "the caller cannot transfer a value of 1337, it has only a free balance of 1336"
+)]
+fn value_transferred_must_not_exceed_caller_balance() {
+    let mut engine = Engine::new();
+    engine.set_caller(vec![1; 32]);
+    engine.set_balance(vec![1; 32], 1336);
+    engine.set_value_transferred(1337);
+}
+
+#[test]
+fn must_fail_when_buffer_too_small() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let key: &[u8; 32] = &[0x42; 32];
+    engine
+        .set_storage(key, &[0x05_u8; 16])
+        .expect("storing must succeed");
+
+    // when
+    let mut small_buffer = [0; 8];
+    let get = engine.get_storage(key, &mut &mut small_buffer[..]);
+    let take = engine.take_storage(key, &mut &mut small_buffer[..]);
+
+    // then
+    assert_eq!(get, Err(Error::BufferTooSmall));
+    assert_eq!(take, Err(Error::BufferTooSmall));
+    assert_eq!(engine.contains_storage(key), Some(16));
+}
+
+#[test]
+fn ecdsa_recovery_test_from_contracts_pallet() {
+    // given
+    let mut engine = Engine::new();
+    #[rustfmt::skip]
+    let signature: [u8; 65] = [
+        161, 234, 203,  74, 147, 96,  51, 212,   5, 174, 231,   9, 142,  48, 137, 201,
+        162, 118, 192,  67, 239, 16,  71, 216, 125,  86, 167, 139,  70,   7,  86, 241,
+         33,  87, 154, 251,  81, 29, 160,   4, 176, 239,  88, 211, 244, 232, 232,  52,
+        211, 234, 100, 115, 230, 47,  80,  44, 152, 166,  62,  50,   8,  13,  86, 175,
+         28,
+    ];
+    #[rustfmt::skip]
+    let message_hash: [u8; 32] = [
+        162, 28, 244, 179, 96, 76, 244, 178, 188,  83, 230, 248, 143, 106,  77, 117,
+        239, 95, 244, 171, 65, 95,  62, 153, 174, 166, 182,  28, 130,  73, 196, 208
+    ];
+
+    // when
+    let mut output = [0; 33];
+    engine
+        .ecdsa_recover(&signature, &message_hash, &mut output)
+        .expect("must work");
+
+    // then
+    #[rustfmt::skip]
+    const EXPECTED_COMPRESSED_PUBLIC_KEY: [u8; 33] = [
+          2, 121, 190, 102, 126, 249, 220, 187, 172, 85, 160,  98, 149, 206, 135, 11,
+          7,   2, 155, 252, 219,  45, 206,  40, 217, 89, 242, 129,  91,  22, 248, 23,
+        152,
+    ];
+    assert_eq!(output, EXPECTED_COMPRESSED_PUBLIC_KEY);
+}
+
+#[test]
+fn ecdsa_recovery_with_secp256k1_crate() {
+    // given
+    let mut engine = Engine::new();
+    let seckey = [
+        59, 148, 11, 85, 134, 130, 61, 253, 2, 174, 59, 70, 27, 180, 51, 107, 94, 203,
+        174, 253, 102, 39, 170, 146, 46, 252, 4, 143, 236, 12, 136, 28,
+    ];
+    let pubkey = PublicKey::from_slice(&[
+        2, 29, 21, 35, 7, 198, 183, 43, 14, 208, 65, 139, 14, 112, 205, 128, 231, 245,
+        41, 91, 141, 134, 245, 114, 45, 63, 82, 19, 251, 210, 57, 79, 54,
+    ])
+    .expect("pubkey creation failed");
+
+    let mut msg_hash = [0; 32];
+    crate::hashing::sha2_256(b"Some message", &mut msg_hash);
+
+    let msg = Message::from_slice(&msg_hash).expect("message creation failed");
+    let seckey = SecretKey::from_slice(&seckey).expect("secret key creation failed");
+    let recoverable_signature: RecoverableSignature =
+        SECP256K1.sign_ecdsa_recoverable(&msg, &seckey);
+
+    let recovery_id = recoverable_signature.serialize_compact().0.to_i32() as u8;
+    let mut signature = recoverable_signature.serialize_compact().1.to_vec();
+    signature.push(recovery_id);
+    let signature_with_recovery_id: [u8; 65] = signature
+        .try_into()
+        .expect("unable to create signature with recovery id");
+
+    // when
+    let mut output = [0; 33];
+    engine
+        .ecdsa_recover(&signature_with_recovery_id, msg.as_ref(), &mut output)
+        .expect("ecdsa recovery failed");
+
+    // then
+    assert_eq!(output, pubkey.serialize());
+}
+
+#[test]
+fn ecdsa_to_eth_address_works() {
+    // given
+    // The public key of the secret key `1`.
+    let mut engine = Engine::new();
+    #[rustfmt::skip]
+    let pubkey: [u8; 33] = [
+          2, 121, 190, 102, 126, 249, 220, 187, 172, 85, 160,  98, 149, 206, 135, 11,
+          7,   2, 155, 252, 219,  45, 206,  40, 217, 89, 242, 129,  91,  22, 248, 23,
+        152,
+    ];
+
+    // when
+    let mut output = [0; 20];
+    engine
+        .ecdsa_to_eth_address(&pubkey, &mut output)
+        .expect("must work");
+
+    // then
+    #[rustfmt::skip]
+    const EXPECTED_ETH_ADDRESS: [u8; 20] = [
+        0x7e, 0x5f, 0x45, 0x52, 0x09, 0x1a, 0x69, 0x12, 0x5d, 0x5d,
+        0xfc, 0xb7, 0xb8, 0xc2, 0x65, 0x90, 0x29, 0x39, 0x5b, 0xdf,
+    ];
+    assert_eq!(output, EXPECTED_ETH_ADDRESS);
+    assert_eq!(
+        engine.ecdsa_to_eth_address(&[0; 33], &mut output),
+        Err(Error::EcdsaRecoveryFailed)
+    );
+}
+
+#[test]
+fn sr25519_verify_accepts_valid_and_rejects_tampered_signature() {
+    // given
+    let mut engine = Engine::new();
+    #[rustfmt::skip]
+    let mut signature: [u8; 64] = [
+        184,  49,  74, 238,  78, 165, 102, 252,  22,  92, 156, 176, 124, 118, 168, 116,
+        247,  99,   0,  94,   2,  45,   9, 170,  73, 222, 182,  74,  60,  32,  75,  64,
+         98, 174,  69,  55,  83,  85, 180,  98, 208,  75, 231,  57, 205,  62,   4, 105,
+         26, 136, 172,  17, 123,  99,  90, 255, 228,  54, 115,  63,  30, 207, 205, 131,
+    ];
+    let message = b"hello world";
+    #[rustfmt::skip]
+    let pub_key: [u8; 32] = [
+        212,  53, 147, 199,  21, 253, 211,  28,  97,  20,  26, 189,   4, 169, 159, 214,
+        130,  44, 133,  88, 133,  76, 205, 227, 154,  86, 132, 231, 165, 109, 162, 125,
+    ];
+
+    // when
+    let valid = engine.sr25519_verify(&signature, message, &pub_key);
+    signature[0] ^= 0x01;
+    let tampered = engine.sr25519_verify(&signature, message, &pub_key);
+
+    // then
+    assert_eq!(valid, Ok(()));
+    assert_eq!(tampered, Err(Error::Sr25519VerifyFailed));
+}
+
+#[test]
+fn ed25519_verify_works() {
+    // given
+    // Test vector 1 of RFC 8032, signing the empty message.
+    let mut engine = Engine::new();
+    #[rustfmt::skip]
+    let signature: [u8; 64] = [
+        0xe5, 0x56, 0x43, 0x00, 0xc3, 0x60, 0xac, 0x72, 0x90, 0x86, 0xe2, 0xcc, 0x80, 0x6e, 0x82, 0x8a,
+        0x84, 0x87, 0x7f, 0x1e, 0xb8, 0xe5, 0xd9, 0x74, 0xd8, 0x73, 0xe0, 0x65, 0x22, 0x49, 0x01, 0x55,
+        0x5f, 0xb8, 0x82, 0x15, 0x90, 0xa3, 0x3b, 0xac, 0xc6, 0x1e, 0x39, 0x70, 0x1c, 0xf9, 0xb4, 0x6b,
+        0xd2, 0x5b, 0xf5, 0xf0, 0x59, 0x5b, 0xbe, 0x24, 0x65, 0x51, 0x41, 0x43, 0x8e, 0x7a, 0x10, 0x0b,
+    ];
+    #[rustfmt::skip]
+    let pub_key: [u8; 32] = [
+        0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07, 0x3a,
+        0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25, 0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a,
+    ];
+    // Does not encode a point on the curve.
+    let invalid_pub_key = [2; 32];
+
+    // when
+    let valid = engine.ed25519_verify(&signature, b"", &pub_key);
+    let tampered_message = engine.ed25519_verify(&signature, b"tampered", &pub_key);
+    let invalid_pub_key = engine.ed25519_verify(&signature, b"", &invalid_pub_key);
+
+    // then
+    assert_eq!(valid, Ok(()));
+    assert_eq!(tampered_message, Err(Error::Ed25519VerifyFailed));
+    assert_eq!(invalid_pub_key, Err(Error::Ed25519VerifyFailed));
 }
 
 #[test]
@@ -282,7 +1053,9 @@ fn setting_getting_block_timestamp() {
 
     // when
     engine.advance_block();
-    engine.set_block_timestamp(new_block_timestamp);
+    engine
+        .set_block_timestamp(new_block_timestamp)
+        .expect("block timestamp must increase");
     engine.block_timestamp(output);
 
     // then
@@ -300,7 +1073,9 @@ fn setting_getting_block_number() {
 
     // when
     engine.advance_block();
-    engine.set_block_number(new_block_number);
+    engine
+        .set_block_number(new_block_number)
+        .expect("block number must increase");
     engine.block_number(output);
 
     // then
@@ -308,3 +1083,2295 @@ fn setting_getting_block_number() {
         .expect("decoding value transferred failed");
     assert_eq!(output, new_block_number);
 }
+
+#[test]
+fn advancing_blocks_moves_block_number_and_timestamp() {
+    // given
+    let mut engine = Engine::new();
+    let block_time = engine.chain_spec.block_time;
+    engine
+        .set_block_timestamp(1000)
+        .expect("block timestamp must increase");
+
+    // when
+    engine.advance_blocks(5);
+
+    // then
+    let output = &mut &mut get_buffer()[..];
+    engine.block_timestamp(output);
+    let block_timestamp = <u64 as scale::Decode>::decode(&mut &output[..8])
+        .expect("decoding block timestamp failed");
+    assert_eq!(block_timestamp, 1000 + 5 * block_time);
+    let output = &mut &mut get_buffer()[..];
+    engine.block_number(output);
+    let block_number = <u32 as scale::Decode>::decode(&mut &output[..4])
+        .expect("decoding block number failed");
+    assert_eq!(block_number, 5);
+}
+
+#[test]
+fn advancing_blocks_up_to_the_maximum_timestamp() {
+    // given
+    let mut engine = Engine::new();
+    let block_time = engine.chain_spec.block_time;
+    engine
+        .set_block_timestamp(u64::MAX - 2 * block_time)
+        .expect("block timestamp must increase");
+
+    // when
+    engine.advance_blocks(2);
+
+    // then
+    let output = &mut &mut get_buffer()[..];
+    engine.block_timestamp(output);
+    let block_timestamp = <u64 as scale::Decode>::decode(&mut &output[..8])
+        .expect("decoding block timestamp failed");
+    assert_eq!(block_timestamp, u64::MAX);
+}
+
+#[test]
+fn zero_block_time_keeps_the_timestamp() {
+    // given
+    let mut engine = Engine::new();
+    engine.chain_spec.block_time = 0;
+    engine
+        .set_block_timestamp(u64::MAX)
+        .expect("block timestamp must increase");
+
+    // when
+    engine.advance_blocks(3);
+
+    // then
+    let output = &mut &mut get_buffer()[..];
+    engine.block_timestamp(output);
+    let block_timestamp = <u64 as scale::Decode>::decode(&mut &output[..8])
+        .expect("decoding block timestamp failed");
+    assert_eq!(block_timestamp, u64::MAX);
+}
+
+#[test]
+fn advancing_blocks_past_the_maximum_timestamp_panics_without_advancing() {
+    // given
+    let mut engine = Engine::new();
+    let block_time = engine.chain_spec.block_time;
+    engine
+        .set_block_timestamp(u64::MAX - block_time)
+        .expect("block timestamp must increase");
+
+    // when
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        engine.advance_blocks(2)
+    }));
+
+    // then
+    let message = res
+        .expect_err("advancing must overflow")
+        .downcast::<String>()
+        .expect("the panic message must be a string");
+    assert!(
+        message.contains("overflows"),
+        "unexpected message: {message}"
+    );
+    let output = &mut &mut get_buffer()[..];
+    engine.block_number(output);
+    let block_number = <u32 as scale::Decode>::decode(&mut &output[..4])
+        .expect("decoding block number failed");
+    assert_eq!(block_number, 0);
+}
+
+#[test]
+#[should_panic(expected = "of block time 18446744073709551615 overflows")]
+fn huge_block_time_overflows_on_a_single_step() {
+    let mut engine = Engine::new();
+    engine.chain_spec.block_time = u64::MAX;
+    engine.advance_block();
+    engine.advance_block();
+}
+
+#[test]
+fn block_number_and_timestamp_must_not_decrease() {
+    // given
+    let mut engine = Engine::new();
+    engine.advance_blocks(2);
+
+    // when
+    let block_number = engine.set_block_number(1);
+    let block_timestamp = engine.set_block_timestamp(0);
+
+    // then
+    assert_eq!(block_number, Err(crate::Error::DecreasingBlockNumber));
+    assert_eq!(block_timestamp, Err(crate::Error::DecreasingBlockTimestamp));
+    assert_eq!(engine.set_block_number(2), Ok(()));
+}
+
+/// Account of the contract calling into `CALLEE` in the cross-contract call tests.
+const CALLER: [u8; 32] = [10; 32];
+
+/// Account of the contract being called in the cross-contract call tests.
+const CALLEE: [u8; 32] = [11; 32];
+
+/// Does nothing, used for contracts which are never instantiated.
+fn noop(_engine: &mut Engine) {}
+
+/// Returns the doubled `u32` passed as input.
+fn callee_call(engine: &mut Engine) {
+    let value = <u32 as scale::Decode>::decode(&mut &engine.exec_context.input[..])
+        .expect("input must be an encoded `u32`");
+    engine.exec_context.output = scale::Encode::encode(&(value * 2));
+}
+
+/// Calls `CALLEE` with its own input and returns what `CALLEE` returned.
+fn caller_call(engine: &mut Engine) {
+    let input = engine.exec_context.input.clone();
+    let mut output = [0; 4];
+    engine
+        .call(
+            CallFlags::new(),
+            &CALLEE,
+            Weight::zero(),
+            &scale::Encode::encode(&0u128),
+            &input[..],
+            &mut &mut output[..],
+        )
+        .expect("call into `CALLEE` must succeed");
+    engine.exec_context.output = output.to_vec();
+}
+
+/// Reads its input twice via the `input` host function and returns it.
+fn input_echo_call(engine: &mut Engine) {
+    let mut first = [0; 4];
+    engine.input(&mut &mut first[..]);
+    let mut second = [0; 4];
+    engine.input(&mut &mut second[..]);
+    assert_eq!(first, second, "the input must not change when read again");
+    engine.exec_context.output = first.to_vec();
+}
+
+#[test]
+fn input_host_function_returns_the_input_of_the_call() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, input_echo_call);
+    engine.set_callee(vec![1; 32]);
+    engine.set_input(&[9; 4]);
+
+    // when
+    let mut output = [0; 4];
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLEE,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[1, 2, 3, 4],
+        &mut &mut output[..],
+    );
+
+    // then
+    assert_eq!(res, Ok(0));
+    assert_eq!(output, [1, 2, 3, 4]);
+    let mut input = [0; 4];
+    engine.input(&mut &mut input[..]);
+    assert_eq!(input, [9; 4]);
+}
+
+#[test]
+fn call_returns_output_of_nested_call() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLER, &[1; 32], noop, caller_call);
+    engine.register_contract(&CALLEE, &[2; 32], noop, callee_call);
+    engine.set_callee(vec![1; 32]);
+
+    // when
+    let mut output = [0; 4];
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLER,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &scale::Encode::encode(&21u32),
+        &mut &mut output[..],
+    );
+
+    // then
+    assert_eq!(res, Ok(0));
+    let output =
+        <u32 as scale::Decode>::decode(&mut &output[..]).expect("decoding output failed");
+    assert_eq!(output, 42);
+    assert_eq!(engine.get_callee(), vec![1; 32]);
+}
+
+#[test]
+fn call_fails_for_non_contract_and_insufficient_balance() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, callee_call);
+    engine.set_callee(vec![1; 32]);
+    engine.set_balance(vec![1; 32], 10);
+    let input = scale::Encode::encode(&21u32);
+    let output = &mut &mut get_buffer()[..];
+
+    // when
+    let not_callable = engine.call(
+        CallFlags::new(),
+        &[3; 32],
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &input,
+        output,
+    );
+    let too_much_value = engine.call(
+        CallFlags::new(),
+        &CALLEE,
+        Weight::zero(),
+        &scale::Encode::encode(&11u128),
+        &input,
+        output,
+    );
+
+    // then
+    assert_eq!(not_callable, Err(Error::NotCallable));
+    assert_eq!(too_much_value, Err(Error::TransferFailed));
+    assert_eq!(engine.get_balance(vec![1; 32]), Ok(10));
+}
+
+/// Returns the input passed to the constructor as output.
+fn echo_deploy(engine: &mut Engine) {
+    engine.exec_context.output = engine.exec_context.input.clone();
+}
+
+#[test]
+fn instantiate_derives_distinct_addresses_per_salt() {
+    // given
+    let mut engine = Engine::new();
+    let code_hash = [2; 32];
+    engine.register_contract(&CALLEE, &code_hash, echo_deploy, noop);
+    engine.set_callee(vec![1; 32]);
+    let endowment = scale::Encode::encode(&0u128);
+    let mut addresses = Vec::new();
+
+    // when
+    for salt in [[0u8; 4], [1u8; 4]] {
+        let mut address = [0; 32];
+        let mut return_value = [0; 3];
+        let res = engine.instantiate(
+            &code_hash,
+            Weight::zero(),
+            &endowment,
+            &[7, 8, 9],
+            &mut &mut address[..],
+            &mut &mut return_value[..],
+            &salt,
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(return_value, [7, 8, 9]);
+        addresses.push(address);
+    }
+
+    // then
+    assert_ne!(addresses[0], addresses[1]);
+    assert_eq!(engine.database.nonce(&[1; 32]), 2);
+    let duplicate = engine.instantiate(
+        &code_hash,
+        Weight::zero(),
+        &endowment,
+        &[7, 8, 9],
+        &mut &mut get_buffer()[..],
+        &mut &mut get_buffer()[..],
+        &[0; 4],
+    );
+    assert_eq!(duplicate, Err(Error::DuplicateContract));
+    assert_eq!(engine.get_callee(), vec![1; 32]);
+    // a failed instantiation leaves the nonce untouched
+    assert_eq!(engine.database.nonce(&[1; 32]), 2);
+}
+
+#[test]
+fn gas_left_reflects_charged_host_functions() {
+    // given
+    let gas_model = GasModel::new(1).with_weight("set_storage", 10);
+    let mut engine = Engine::new_with_gas_model(gas_model);
+    engine.set_callee(vec![1; 32]);
+    engine.set_gas_limit(100);
+    let key: &[u8; 32] = &[0x42; 32];
+
+    // when
+    engine
+        .set_storage(key, &[0x05_u8; 5])
+        .expect("storing must succeed");
+    engine.get_storage(key, &mut &mut get_buffer()[..]).unwrap();
+    let output = &mut &mut get_buffer()[..];
+    engine.gas_left(output);
+
+    // then
+    assert_eq!(engine.gas_consumed(), 12);
+    let gas_left = <u64 as scale::Decode>::decode(&mut &output[..8])
+        .expect("decoding gas left failed");
+    assert_eq!(gas_left, 88);
+}
+
+/// Writes to its storage and traps afterwards.
+fn trapping_call(engine: &mut Engine) {
+    engine
+        .set_storage(&[0x42; 32], &[0x05_u8; 5])
+        .expect("storing must succeed");
+    panic!("contract trapped");
+}
+
+#[test]
+fn trapped_call_reverts_state_changes() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, trapping_call);
+    engine.set_callee(vec![1; 32]);
+    engine.set_balance(vec![1; 32], 3_000_000);
+
+    // when
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLEE,
+        Weight::zero(),
+        &scale::Encode::encode(&1_000_000u128),
+        &[],
+        &mut &mut get_buffer()[..],
+    );
+
+    // then
+    assert_eq!(res, Err(Error::CalleeTrapped));
+    assert_eq!(engine.get_balance(vec![1; 32]), Ok(3_000_000));
+    assert_eq!(engine.get_balance(CALLEE.to_vec()).ok(), None);
+    assert_eq!(engine.iter_contract_storage(&CALLEE).count(), 0);
+    assert_eq!(engine.get_callee(), vec![1; 32]);
+}
+
+#[test]
+fn reserved_balance_cannot_be_transferred() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let ed = minimum_balance(&mut engine);
+    engine.set_callee(alice.clone());
+    engine.set_balance(alice.clone(), ed + 100);
+    let encoded = |value: u128| scale::Encode::encode(&value);
+
+    // when
+    assert_eq!(
+        engine.reserve(&alice, &encoded(ed + 101)),
+        Err(Error::TransferFailed)
+    );
+    engine
+        .reserve(&alice, &encoded(60))
+        .expect("reserving must work");
+
+    // then
+    assert_eq!(engine.get_balance(alice.clone()), Ok(ed + 40));
+    assert_eq!(engine.reserved_balance(&alice), 60);
+    assert_eq!(
+        engine.transfer(&[2; 32], &encoded(ed + 41)),
+        Err(Error::TransferFailed)
+    );
+    assert_eq!(
+        engine.unreserve(&alice, &encoded(61)),
+        Err(Error::TransferFailed)
+    );
+    engine
+        .unreserve(&alice, &encoded(20))
+        .expect("unreserving must work");
+    assert_eq!(engine.get_balance(alice.clone()), Ok(ed + 60));
+    assert_eq!(engine.reserved_balance(&alice), 40);
+}
+
+#[test]
+fn reserve_must_not_leave_free_balance_with_dust() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let ed = minimum_balance(&mut engine);
+    engine.set_balance(alice.clone(), ed + 10);
+    let encoded = |value: u128| scale::Encode::encode(&value);
+
+    // then
+    assert_eq!(
+        engine.reserve(&alice, &encoded(11)),
+        Err(Error::TransferFailed)
+    );
+    assert_eq!(engine.get_balance(alice.clone()), Ok(ed + 10));
+    assert_eq!(engine.reserved_balance(&alice), 0);
+    assert_eq!(engine.reserve(&alice, &encoded(10)), Ok(()));
+    assert_eq!(engine.reserve(&alice, &encoded(ed)), Ok(()));
+    assert_eq!(engine.get_balance(alice.clone()), Ok(0));
+    assert_eq!(engine.reserved_balance(&alice), ed + 10);
+}
+
+#[test]
+fn reserve_and_unreserve_fail_if_the_balance_overflows() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let encoded = |value: u128| scale::Encode::encode(&value);
+    engine.set_balance(alice.clone(), u128::MAX);
+    engine
+        .reserve(&alice, &encoded(u128::MAX))
+        .expect("reserving must work");
+
+    // when
+    engine.set_balance(alice.clone(), u128::MAX);
+
+    // then
+    assert_eq!(
+        engine.reserve(&alice, &encoded(1)),
+        Err(Error::TransferFailed)
+    );
+    assert_eq!(
+        engine.unreserve(&alice, &encoded(1)),
+        Err(Error::TransferFailed)
+    );
+    assert_eq!(engine.get_balance(alice.clone()), Ok(u128::MAX));
+    assert_eq!(engine.reserved_balance(&alice), u128::MAX);
+}
+
+/// Calls itself until the maximum call depth is reached.
+///
+/// Outputs the call depth at which the nested call trapped.
+fn recursive_call(engine: &mut Engine) {
+    let depth = engine.call_depth();
+    let mut output = get_buffer();
+    let res = engine.call(
+        CallFlags::new().allow_reentry(),
+        &CALLEE,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut output[..],
+    );
+    engine.exec_context.output = match res {
+        Ok(_) => output[..4].to_vec(),
+        Err(Error::CalleeTrapped) => scale::Encode::encode(&depth),
+        Err(err) => panic!("unexpected error: {err:?}"),
+    };
+}
+
+#[test]
+fn call_traps_when_exceeding_max_call_depth() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, recursive_call);
+    engine.set_callee(CALLER.to_vec());
+    engine.set_max_call_depth(4);
+
+    // when
+    let mut output = get_buffer();
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLEE,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut output[..],
+    );
+
+    // then
+    assert_eq!(res, Ok(0));
+    let depth: u32 = scale::Decode::decode(&mut &output[..4]).expect("decoding failed");
+    assert_eq!(depth, 4);
+    assert_eq!(engine.call_depth(), 0);
+}
+
+/// Instantiates a contract from its own code until the maximum call depth is
+/// reached.
+///
+/// Outputs the call depth at which the nested instantiation trapped.
+fn recursive_deploy(engine: &mut Engine) {
+    let depth = engine.call_depth();
+    let mut return_value = get_buffer();
+    let res = engine.instantiate(
+        &[2; 32],
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut get_buffer()[..],
+        &mut &mut return_value[..],
+        &[],
+    );
+    engine.exec_context.output = match res {
+        Ok(()) => return_value[..4].to_vec(),
+        Err(Error::CalleeTrapped) => scale::Encode::encode(&depth),
+        Err(err) => panic!("unexpected error: {err:?}"),
+    };
+}
+
+#[test]
+fn instantiate_traps_when_exceeding_max_call_depth() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], recursive_deploy, noop);
+    engine.set_callee(CALLER.to_vec());
+    engine.set_max_call_depth(4);
+
+    // when
+    let mut return_value = get_buffer();
+    let res = engine.instantiate(
+        &[2; 32],
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut get_buffer()[..],
+        &mut &mut return_value[..],
+        &[],
+    );
+
+    // then
+    assert_eq!(res, Ok(()));
+    let depth: u32 =
+        scale::Decode::decode(&mut &return_value[..4]).expect("decoding failed");
+    assert_eq!(depth, 4);
+    assert_eq!(engine.call_depth(), 0);
+}
+
+#[test]
+fn set_code_hash_upgrades_contract_code() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, noop);
+    engine.register_contract(&[12; 32], &[3; 32], noop, callee_call);
+    let mut output = get_buffer();
+    assert_eq!(engine.code_hash(&CALLEE, &mut &mut output[..]), Ok(()));
+    assert_eq!(&output[..32], &[2; 32]);
+
+    // when
+    assert_eq!(
+        engine.set_code_hash(&CALLEE, &[4; 32]),
+        Err(Error::CodeNotFound)
+    );
+    assert_eq!(engine.set_code_hash(&CALLEE, &[3; 32]), Ok(()));
+
+    // then
+    assert_eq!(engine.code_hash(&CALLEE, &mut &mut output[..]), Ok(()));
+    assert_eq!(&output[..32], &[3; 32]);
+    assert_eq!(
+        engine.code_hash(&[13; 32], &mut &mut output[..]),
+        Err(Error::CodeNotFound)
+    );
+}
+
+#[test]
+fn own_code_hash_returns_code_hash_of_callee() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, noop);
+    let mut output = get_buffer();
+
+    // when
+    engine.set_callee(CALLER.to_vec());
+    let not_a_contract = engine.own_code_hash(&mut &mut output[..]);
+    engine.set_callee(CALLEE.to_vec());
+    let res = engine.own_code_hash(&mut &mut output[..]);
+
+    // then
+    assert_eq!(not_a_contract, Err(Error::CodeNotFound));
+    assert_eq!(res, Ok(()));
+    assert_eq!(&output[..32], &[2; 32]);
+}
+
+#[test]
+fn is_contract_works() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, noop);
+    engine.set_contract(vec![12; 32]);
+
+    // then
+    assert!(engine.is_contract(&CALLEE));
+    assert!(engine.is_contract(&[12; 32]));
+    assert!(!engine.is_contract(&[13; 32]));
+}
+
+/// Outputs whether its caller is the origin and whether it is root.
+fn origin_call(engine: &mut Engine) {
+    engine.exec_context.output = vec![
+        engine.caller_is_origin() as u8,
+        engine.caller_is_root() as u8,
+    ];
+}
+
+#[test]
+fn caller_is_origin_only_for_direct_calls() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, origin_call);
+    engine.set_caller(vec![1; 32]);
+    engine.set_origin(vec![1; 32]);
+    engine.set_callee(CALLER.to_vec());
+    engine.set_root(true);
+
+    // when
+    let direct = (engine.caller_is_origin(), engine.caller_is_root());
+    let mut output = get_buffer();
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLEE,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut output[..],
+    );
+
+    // then
+    assert_eq!(res, Ok(0));
+    assert_eq!(direct, (true, true));
+    assert_eq!(&output[..2], &[0, 0]);
+    engine.set_root(false);
+    assert_eq!(
+        (engine.caller_is_origin(), engine.caller_is_root()),
+        (true, false)
+    );
+    engine.set_caller(vec![2; 32]);
+    assert!(!engine.caller_is_origin());
+}
+
+/// Returns the address written by the `caller` host function.
+fn caller_of(engine: &mut Engine) -> Vec<u8> {
+    let mut caller = [0; 32];
+    engine.caller(&mut &mut caller[..]);
+    caller.to_vec()
+}
+
+/// Returns its caller.
+fn caller_reporting_call(engine: &mut Engine) {
+    engine.exec_context.output = caller_of(engine);
+}
+
+/// Returns its caller before calling `CALLEE`, the caller of `CALLEE` and its
+/// caller after the call into `CALLEE`.
+fn nested_caller_reporting_call(engine: &mut Engine) {
+    let before = caller_of(engine);
+    let mut nested = [0; 32];
+    engine
+        .call(
+            CallFlags::new(),
+            &CALLEE,
+            Weight::zero(),
+            &scale::Encode::encode(&0u128),
+            &[],
+            &mut &mut nested[..],
+        )
+        .expect("call into `CALLEE` must succeed");
+    let after = caller_of(engine);
+    engine.exec_context.output = [before, nested.to_vec(), after].concat();
+}
+
+#[test]
+fn caller_reflects_the_calling_frame_at_each_depth() {
+    // given
+    let mut engine = Engine::new();
+    let origin = vec![1; 32];
+    let top_level = vec![5; 32];
+    engine.register_contract(&CALLER, &[1; 32], noop, nested_caller_reporting_call);
+    engine.register_contract(&CALLEE, &[2; 32], noop, caller_reporting_call);
+    engine.set_caller(origin.clone());
+    engine.set_callee(top_level.clone());
+
+    // when
+    let before = caller_of(&mut engine);
+    let mut output = [0; 96];
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLER,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut output[..],
+    );
+    let after = caller_of(&mut engine);
+
+    // then
+    assert_eq!(res, Ok(0));
+    assert_eq!(before, origin);
+    assert_eq!(&output[..32], &top_level[..]);
+    assert_eq!(&output[32..64], &CALLER[..]);
+    assert_eq!(&output[64..], &top_level[..]);
+    assert_eq!(after, origin);
+}
+
+#[test]
+fn random_is_deterministic_per_seed_subject_and_block() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_random_seed([7; 32]);
+    let random = |engine: &mut Engine, subject: &[u8]| {
+        let mut output = [0; 32];
+        engine.random(subject, &mut &mut output[..]);
+        output
+    };
+
+    // when
+    let first = random(&mut engine, b"subject");
+    let second = random(&mut engine, b"subject");
+    let other_subject = random(&mut engine, b"other subject");
+    engine.advance_block();
+    let next_block = random(&mut engine, b"subject");
+    engine.set_random_seed([8; 32]);
+    let other_seed = random(&mut engine, b"subject");
+
+    // then
+    assert_eq!(first, second);
+    assert_ne!(first, other_subject);
+    assert_ne!(first, next_block);
+    assert_ne!(next_block, other_seed);
+}
+
+#[test]
+fn host_function_calls_are_counted() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let key: &[u8; 32] = &[0x42; 32];
+
+    // when
+    engine
+        .set_storage(key, &[0x05_u8; 5])
+        .expect("storing must succeed");
+    let mut output = get_buffer();
+    engine
+        .get_storage(key, &mut &mut output[..])
+        .expect("storage must exist");
+    engine
+        .get_storage(key, &mut &mut output[..])
+        .expect("storage must exist");
+    engine.block_number(&mut &mut get_buffer()[..]);
+
+    // then
+    assert_eq!(engine.host_fn_calls("set_storage"), 1);
+    assert_eq!(engine.host_fn_calls("get_storage"), 2);
+    assert_eq!(engine.host_fn_calls("block_number"), 1);
+    assert_eq!(engine.host_fn_calls("transfer"), 0);
+    engine.initialize_or_reset();
+    assert_eq!(engine.host_fn_calls("get_storage"), 0);
+}
+
+#[test]
+fn clear_storage_prefix_removes_only_prefixed_keys() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    for key in [&[1, 1][..], &[1, 2], &[1, 2, 3], &[2, 1], &[0, 1]] {
+        engine
+            .set_storage(key, &[0x05_u8; 5])
+            .expect("storing must succeed");
+    }
+    let (_, writes_before) = engine.get_contract_storage_rw(vec![1; 32]);
+
+    // when
+    let removed = engine.clear_storage_prefix(&[1]);
+
+    // then
+    assert_eq!(removed, 3);
+    let (_, writes_after) = engine.get_contract_storage_rw(vec![1; 32]);
+    assert_eq!(writes_after, writes_before + 3);
+    assert_eq!(engine.contains_storage(&[1, 1]), None);
+    assert_eq!(engine.contains_storage(&[1, 2, 3]), None);
+    assert_eq!(engine.contains_storage(&[2, 1]), Some(5));
+    assert_eq!(engine.contains_storage(&[0, 1]), Some(5));
+    assert_eq!(engine.clear_storage_prefix(&[1]), 0);
+}
+
+#[test]
+fn weight_to_fee_accounts_for_both_weight_dimensions() {
+    // given
+    let mut engine = Engine::new();
+    let fee = |engine: &mut Engine, ref_time, proof_size| {
+        let mut output = get_buffer();
+        engine.weight_to_fee_v2(ref_time, proof_size, &mut &mut output[..]);
+        <u128 as scale::Decode>::decode(&mut &output[..16]).expect("decoding failed")
+    };
+    let mut output = get_buffer();
+    engine.weight_to_fee(Weight::from(7), &mut &mut output[..]);
+    let legacy_fee =
+        <u128 as scale::Decode>::decode(&mut &output[..16]).expect("decoding failed");
+
+    // then
+    assert_eq!(legacy_fee, 700);
+    assert_eq!(fee(&mut engine, 7, 0), legacy_fee);
+    assert_eq!(fee(&mut engine, 0, 3), 30);
+    assert_eq!(fee(&mut engine, 7, 3), 730);
+}
+
+#[test]
+fn chain_spec_can_be_configured_via_the_engine() {
+    // given
+    let fee = |engine: &mut Engine| {
+        let mut output = get_buffer();
+        engine.weight_to_fee(Weight::from(7), &mut &mut output[..]);
+        <u128 as scale::Decode>::decode(&mut &output[..16]).expect("decoding failed")
+    };
+    let mut engine = Engine::with_chain_spec(ChainSpec {
+        gas_price: 3,
+        ..Default::default()
+    });
+
+    // when
+    let configured = fee(&mut engine);
+    engine.set_gas_price(5);
+    engine.set_block_time(0);
+    engine.advance_block();
+
+    // then
+    assert_eq!(configured, 21);
+    assert_eq!(fee(&mut engine), 35);
+    let output = &mut &mut get_buffer()[..];
+    engine.block_timestamp(output);
+    let block_timestamp = <u64 as scale::Decode>::decode(&mut &output[..8])
+        .expect("decoding block timestamp failed");
+    assert_eq!(block_timestamp, 0);
+}
+
+#[test]
+fn weights_convert_from_and_to_gas() {
+    // given
+    let mut engine = Engine::new();
+    let weight = Weight::from_parts(7, 3);
+
+    // when
+    let mut output = get_buffer();
+    engine.weight_to_fee(weight, &mut &mut output[..]);
+    let fee =
+        <u128 as scale::Decode>::decode(&mut &output[..16]).expect("decoding failed");
+
+    // then
+    assert_eq!(fee, 730);
+    assert_eq!(engine.weight_to_fee_checked(weight), Some(730));
+    assert_eq!(Weight::from(7), Weight::from_parts(7, 0));
+    assert_eq!(u64::from(weight), 7);
+    assert_eq!(Weight::zero(), Weight::default());
+    engine.chain_spec.storage_price = u128::MAX;
+    assert_eq!(engine.weight_to_fee_checked(weight), None);
+    assert_eq!(engine.weight_to_fee_checked(Weight::from(7)), Some(700));
+}
+
+#[test]
+fn weight_to_fee_at_the_overflow_boundary() {
+    // given
+    let mut engine = Engine::new();
+    let saturating_fee = |engine: &mut Engine, gas: u64| {
+        let mut output = get_buffer();
+        engine.weight_to_fee(gas.into(), &mut &mut output[..]);
+        <u128 as scale::Decode>::decode(&mut &output[..16]).expect("decoding failed")
+    };
+    // the largest gas price for which the fee of `u64::MAX` gas does not overflow
+    let max_gas_price = u128::MAX / u128::from(u64::MAX);
+
+    // when
+    engine.chain_spec.gas_price = max_gas_price;
+
+    // then
+    assert_eq!(
+        engine.weight_to_fee_checked(Weight::from(u64::MAX)),
+        Some(u128::MAX)
+    );
+    assert_eq!(saturating_fee(&mut engine, u64::MAX), u128::MAX);
+
+    // when
+    engine.chain_spec.gas_price = max_gas_price + 1;
+
+    // then
+    assert_eq!(engine.weight_to_fee_checked(Weight::from(u64::MAX)), None);
+    assert_eq!(saturating_fee(&mut engine, u64::MAX), u128::MAX);
+    assert_eq!(
+        engine.weight_to_fee_checked(Weight::from(u64::MAX - 1)),
+        Some((max_gas_price + 1) * u128::from(u64::MAX - 1))
+    );
+}
+
+#[test]
+fn take_storage_checked_fails_for_too_small_buffer() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let key: &[u8; 32] = &[0x42; 32];
+    engine
+        .set_storage(key, &[0x05_u8; 16])
+        .expect("storing must succeed");
+
+    // when
+    let mut small = [0; 8];
+    let res = engine.take_storage_checked(key, &mut &mut small[..]);
+
+    // then
+    assert_eq!(res, Err(Error::BufferTooSmall));
+    assert_eq!(engine.contains_storage(key), Some(16));
+    let mut output = get_buffer();
+    assert_eq!(
+        engine.take_storage_checked(key, &mut &mut output[..]),
+        Ok(())
+    );
+    assert_eq!(&output[..16], &[0x05_u8; 16]);
+    assert_eq!(engine.contains_storage(key), None);
+}
+
+/// A chain extension method which echoes its raw input.
+struct EchoExtension;
+
+impl ChainExtension for EchoExtension {
+    fn func_id(&self) -> u32 {
+        1
+    }
+
+    fn call(&mut self, input: &[u8], output: &mut Vec<u8>) -> u32 {
+        output.extend_from_slice(input);
+        7
+    }
+}
+
+/// Calls the chain extension method `func_id` with the encoded `input`.
+///
+/// Returns the status code and the output of the chain extension method.
+fn call_chain_extension(
+    engine: &mut Engine,
+    func_id: u32,
+    input: &[u8],
+) -> (u32, Vec<u8>) {
+    let mut output = get_buffer();
+    engine
+        .call_chain_extension(func_id, input, &mut &mut output[..])
+        .expect("chain extension method must be registered");
+    scale::Decode::decode(&mut &output[..]).expect("decoding failed")
+}
+
+#[test]
+fn chain_extensions_can_be_registered_raw_and_typed() {
+    // given
+    let mut engine = Engine::new();
+    engine
+        .chain_extension_handler
+        .register(Box::new(EchoExtension));
+    engine
+        .chain_extension_handler
+        .register_typed(2, |(a, b): (u32, u32)| (0, u64::from(a) + u64::from(b)));
+
+    // when
+    let raw = call_chain_extension(&mut engine, 1, &[1, 2, 3]);
+    let typed =
+        call_chain_extension(&mut engine, 2, &scale::Encode::encode(&(3u32, 4u32)));
+
+    // then
+    // the raw input is passed as SCALE encoded byte vector
+    assert_eq!(raw, (7, scale::Encode::encode(&vec![1u8, 2, 3])));
+    assert_eq!(typed, (0, scale::Encode::encode(&7u64)));
+}
+
+#[test]
+fn chain_extension_stubs_can_hold_state() {
+    // given
+    let mut engine = Engine::new();
+    let mut calls = 0u32;
+    engine.set_chain_extension_return(3, move || {
+        let result = calls;
+        calls += 1;
+        (0, result)
+    });
+
+    // when
+    let results: Vec<_> = (0..3)
+        .map(|_| call_chain_extension(&mut engine, 3, &[1, 2, 3]))
+        .collect();
+
+    // then
+    assert_eq!(
+        results,
+        vec![
+            (0, scale::Encode::encode(&0u32)),
+            (0, scale::Encode::encode(&1u32)),
+            (0, scale::Encode::encode(&2u32)),
+        ]
+    );
+}
+
+/// A chain extension whose methods return the extension's `tag`, the ID of the
+/// called method and the number of calls to the extension so far.
+struct TaggedExtension {
+    tag: u8,
+    calls: u32,
+}
+
+impl Extension for TaggedExtension {
+    fn call(&mut self, func_id: u16, _input: &[u8], output: &mut Vec<u8>) -> u32 {
+        self.calls += 1;
+        scale::Encode::encode_to(&(self.tag, func_id, self.calls), output);
+        0
+    }
+}
+
+#[test]
+fn chain_extensions_are_routed_by_extension_id() {
+    // given
+    let mut engine = Engine::new();
+    for tag in [1, 2] {
+        engine
+            .chain_extension_handler
+            .register_extension(u16::from(tag), TaggedExtension { tag, calls: 0 });
+    }
+    let mut call = |func_id: u32| {
+        let (status_code, output) = call_chain_extension(&mut engine, func_id, &[]);
+        assert_eq!(status_code, 0);
+        scale::Decode::decode(&mut &output[..]).expect("decoding failed")
+    };
+
+    // when
+    let first: (u8, u16, u32) = call(1 << 16 | 5);
+    let second: (u8, u16, u32) = call(2 << 16 | 5);
+    let third: (u8, u16, u32) = call(1 << 16 | 6);
+
+    // then
+    assert_eq!(first, (1, 5, 1));
+    assert_eq!(second, (2, 5, 1));
+    assert_eq!(third, (1, 6, 2));
+    let mut output = get_buffer();
+    assert!(matches!(
+        engine.call_chain_extension(3 << 16 | 5, &[], &mut &mut output[..]),
+        Err(crate::Error::UnregisteredChainExtension)
+    ));
+}
+
+/// A chain extension object implementing a counter with an `increment` (1) and a
+/// `get` (2) method.
+#[derive(Default)]
+struct CounterExtension {
+    count: u32,
+}
+
+impl ChainExtensionObject for CounterExtension {
+    fn call(&mut self, func_id: u32, _input: &[u8]) -> (u32, Vec<u8>) {
+        match func_id {
+            1 => {
+                self.count += 1;
+                (0, Vec::new())
+            }
+            2 => (0, scale::Encode::encode(&self.count)),
+            _ => (1, Vec::new()),
+        }
+    }
+}
+
+#[test]
+fn chain_extension_object_handles_unregistered_functions() {
+    // given
+    let mut engine = Engine::new();
+    engine
+        .chain_extension_handler
+        .register_object(Box::<CounterExtension>::default());
+    engine
+        .chain_extension_handler
+        .register_typed(3, |()| (0, 42u32));
+
+    // when
+    call_chain_extension(&mut engine, 1, &[]);
+    call_chain_extension(&mut engine, 1, &[]);
+
+    // then
+    assert_eq!(
+        call_chain_extension(&mut engine, 2, &[]),
+        (0, scale::Encode::encode(&2u32))
+    );
+    assert_eq!(
+        call_chain_extension(&mut engine, 3, &[]),
+        (0, scale::Encode::encode(&42u32))
+    );
+    assert_eq!(call_chain_extension(&mut engine, 4, &[]), (1, Vec::new()));
+}
+
+#[test]
+fn calling_unregistered_chain_extension_fails() {
+    // given
+    let mut engine = Engine::new();
+    engine
+        .chain_extension_handler
+        .register(Box::new(EchoExtension));
+    let mut output = get_buffer();
+
+    // when
+    let res = engine.call_chain_extension(42, &[1, 2, 3], &mut &mut output[..]);
+
+    // then
+    assert!(matches!(res, Err(crate::Error::UnregisteredChainExtension)));
+    assert_eq!(output, get_buffer());
+}
+
+#[test]
+fn transfer_in_credits_callee_balance() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(vec![1; 32]);
+    engine.set_callee(vec![2; 32]);
+    engine.set_balance(vec![1; 32], 100);
+    engine.set_balance(vec![2; 32], 50);
+
+    // when
+    engine.transfer_in(30);
+
+    // then
+    let mut output = get_buffer();
+    engine.balance(&mut &mut output[..]);
+    let balance =
+        <u128 as scale::Decode>::decode(&mut &output[..16]).expect("decoding failed");
+    engine.value_transferred(&mut &mut output[..]);
+    let value =
+        <u128 as scale::Decode>::decode(&mut &output[..16]).expect("decoding failed");
+    assert_eq!(balance, 80);
+    assert_eq!(value, 30);
+    assert_eq!(engine.get_balance(vec![1; 32]), Ok(70));
+}
+
+#[test]
+#[should_panic(
+    expected = "transferring in 1 overflows the callee balance of 340282366920938463463374607431768211455"
+)]
+fn transfer_in_panics_on_callee_balance_overflow() {
+    let mut engine = Engine::new();
+    engine.set_caller(vec![1; 32]);
+    engine.set_callee(vec![2; 32]);
+    engine.set_balance(vec![1; 32], 100);
+    engine.set_balance(vec![2; 32], u128::MAX);
+    engine.transfer_in(1);
+}
+
+#[test]
+fn transfer_in_credits_callee_up_to_max_balance() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(vec![1; 32]);
+    engine.set_callee(vec![2; 32]);
+    engine.set_balance(vec![1; 32], 100);
+    engine.set_balance(vec![2; 32], u128::MAX - 1);
+
+    // when
+    engine.transfer_in(1);
+
+    // then
+    assert_eq!(engine.get_balance(vec![1; 32]), Ok(99));
+    assert_eq!(engine.get_balance(vec![2; 32]), Ok(u128::MAX));
+}
+
+#[test]
+fn account_balance_helpers_work() {
+    // given
+    let mut engine = Engine::new();
+    let ed = minimum_balance(&mut engine);
+    engine.set_callee(vec![1; 32]);
+    engine.set_account_balance(&[1; 32], 3 * ed);
+
+    // when
+    engine
+        .transfer(&[2; 32], &scale::Encode::encode(&ed))
+        .expect("transfer must work");
+
+    // then
+    assert_eq!(engine.account_balance(&[1; 32]), Some(2 * ed));
+    assert_eq!(engine.account_balance(&[2; 32]), Some(ed));
+    assert_eq!(engine.account_balance(&[3; 32]), None);
+}
+
+#[test]
+fn transfers_conserve_total_issuance() {
+    // given
+    let mut engine = Engine::new();
+    let ed = minimum_balance(&mut engine);
+    engine.set_callee(vec![1; 32]);
+    engine.set_account_balance(&[1; 32], 3 * ed);
+    engine.set_account_balance(&[2; 32], ed);
+    engine
+        .reserve(&[2; 32], &scale::Encode::encode(&ed))
+        .expect("reserving must work");
+    assert_eq!(engine.total_issuance(), 4 * ed);
+
+    // when
+    engine
+        .transfer(&[3; 32], &scale::Encode::encode(&ed))
+        .expect("transfer must work");
+
+    // then
+    assert_eq!(engine.total_issuance(), 4 * ed);
+    engine.initialize_or_reset();
+    assert_eq!(engine.total_issuance(), 0);
+}
+
+#[test]
+fn mint_and_burn_adjust_total_issuance() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_account_balance(&[1; 32], 100);
+
+    // when
+    engine.mint(&[1; 32], 50);
+    engine.mint(&[2; 32], 10);
+
+    // then
+    assert_eq!(engine.account_balance(&[1; 32]), Some(150));
+    assert_eq!(engine.account_balance(&[2; 32]), Some(10));
+    assert_eq!(engine.total_issuance(), 160);
+
+    assert_eq!(engine.burn(&[1; 32], 30), Ok(()));
+    assert_eq!(engine.account_balance(&[1; 32]), Some(120));
+    assert_eq!(engine.total_issuance(), 130);
+
+    // burning more than the free balance must fail
+    assert_eq!(engine.burn(&[2; 32], 11), Err(Error::TransferFailed));
+    assert_eq!(engine.burn(&[3; 32], 1), Err(Error::TransferFailed));
+    assert_eq!(engine.account_balance(&[2; 32]), Some(10));
+    assert_eq!(engine.total_issuance(), 130);
+}
+
+/// Transfers `1` to `CALLER` and returns whether the transfer succeeded.
+fn transferring_call(engine: &mut Engine) {
+    let succeeded = engine
+        .transfer(&CALLER, &scale::Encode::encode(&1u128))
+        .is_ok();
+    engine.exec_context.output = scale::Encode::encode(&succeeded);
+}
+
+#[test]
+fn injected_transfer_failure_is_observed_once() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, transferring_call);
+    engine.set_callee(vec![1; 32]);
+    engine.set_balance(CALLER.to_vec(), 1_000_000);
+    engine.set_balance(CALLEE.to_vec(), 2_000_000);
+    engine.fault_injector.fail_next_transfer();
+    let call = |engine: &mut Engine| {
+        let mut output = [0; 1];
+        engine
+            .call(
+                CallFlags::new(),
+                &CALLEE,
+                Weight::zero(),
+                &scale::Encode::encode(&0u128),
+                &[],
+                &mut &mut output[..],
+            )
+            .expect("call into `CALLEE` must succeed");
+        <bool as scale::Decode>::decode(&mut &output[..]).expect("decoding failed")
+    };
+
+    // when
+    let first = call(&mut engine);
+    let second = call(&mut engine);
+
+    // then
+    assert!(!first);
+    assert!(second);
+    assert_eq!(engine.get_balance(CALLEE.to_vec()), Ok(1_999_999));
+}
+
+#[test]
+fn injected_chain_extension_status_is_returned_once() {
+    // given
+    let mut engine = Engine::new();
+    engine
+        .chain_extension_handler
+        .register(Box::new(EchoExtension));
+    engine.fault_injector.fail_next_chain_extension(42);
+
+    // when
+    let first = call_chain_extension(&mut engine, 1, &[1, 2, 3]);
+    let second = call_chain_extension(&mut engine, 1, &[1, 2, 3]);
+
+    // then
+    assert_eq!(first, (42, Vec::new()));
+    assert_eq!(second, (7, scale::Encode::encode(&vec![1u8, 2, 3])));
+}
+
+#[test]
+fn reset_clears_state_but_keeps_setup() {
+    // given
+    let mut engine = Engine::new();
+    engine
+        .chain_extension_handler
+        .register(Box::new(EchoExtension));
+    engine.set_max_call_depth(2);
+    engine.register_contract(&CALLEE, &[2; 32], noop, noop);
+    engine.set_callee(vec![1; 32]);
+    engine.set_balance(vec![1; 32], 1_000_000);
+    engine
+        .set_storage(&[0x42; 32], &[0x05_u8; 5])
+        .expect("storing must succeed");
+    engine.deposit_event(&scale::Encode::encode(&vec![[0x01u8; 32]]), &[0x02]);
+
+    // when
+    engine.reset();
+
+    // then
+    assert_eq!(engine.account_balance(&[1; 32]), None);
+    assert_eq!(engine.total_issuance(), 0);
+    assert_eq!(engine.get_emitted_events().count(), 0);
+    assert_eq!(engine.count_writes(), 0);
+    assert!(!engine.is_contract(&CALLEE));
+    assert_eq!(engine.chain_spec.max_call_depth, 2);
+    assert_eq!(
+        call_chain_extension(&mut engine, 1, &[1]),
+        (7, scale::Encode::encode(&vec![1u8]))
+    );
+    engine.set_callee(vec![1; 32]);
+    assert_eq!(engine.iter_contract_storage(&[1; 32]).count(), 0);
+}
+
+#[test]
+fn reset_all_clears_setup() {
+    // given
+    let mut engine = Engine::new();
+    engine
+        .chain_extension_handler
+        .register(Box::new(EchoExtension));
+    engine.set_max_call_depth(2);
+    engine.set_balance(vec![1; 32], 1_000_000);
+
+    // when
+    engine.reset_all();
+
+    // then
+    assert_eq!(engine.account_balance(&[1; 32]), None);
+    assert_eq!(
+        engine.chain_spec.max_call_depth,
+        ChainSpec::default().max_call_depth
+    );
+    let mut output = get_buffer();
+    assert!(matches!(
+        engine.call_chain_extension(1, &[1], &mut &mut output[..]),
+        Err(crate::Error::UnregisteredChainExtension)
+    ));
+}
+
+#[test]
+fn key_access_counts_are_recorded_per_key() {
+    // given
+    let mut engine = Engine::new();
+    let account_id = vec![1; 32];
+    engine.set_callee(account_id.clone());
+    let read_key = [0x01; 32];
+    let written_key = [0x02; 32];
+    engine
+        .set_storage(&read_key, &[0x05_u8; 5])
+        .expect("storing must succeed");
+
+    // when
+    for _ in 0..3 {
+        engine
+            .get_storage(&read_key, &mut &mut get_buffer()[..])
+            .expect("the key must exist");
+    }
+    engine
+        .set_storage(&written_key, &[0x06_u8; 5])
+        .expect("storing must succeed");
+    engine
+        .set_storage(&written_key, &[0x07_u8; 5])
+        .expect("storing must succeed");
+    let _ = engine.clear_storage(&written_key);
+
+    // then
+    assert_eq!(engine.key_access_counts(&account_id, &read_key), (3, 1));
+    assert_eq!(engine.key_access_counts(&account_id, &written_key), (0, 3));
+    assert_eq!(engine.key_access_counts(&account_id, &[0x03; 32]), (0, 0));
+    assert_eq!(engine.key_access_counts(&[2; 32], &read_key), (0, 0));
+    assert_eq!(engine.get_contract_storage_rw(account_id), (3, 4));
+}
+
+/// Terminates the contract of `engine` in favor of `beneficiary` and returns the
+/// decoded outcome of the termination.
+fn terminate(engine: &mut Engine, beneficiary: &[u8]) -> TerminationResult<u128> {
+    let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        engine.terminate(beneficiary)
+    }))
+    .expect_err("terminate must not return");
+    let encoded = payload
+        .downcast_ref::<Vec<u8>>()
+        .expect("the payload must be the encoded termination result");
+    scale::Decode::decode(&mut &encoded[..]).expect("decoding failed")
+}
+
+#[test]
+fn terminate_reports_transferred_balance() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(CALLEE.to_vec());
+    engine.set_balance(CALLEE.to_vec(), 2_000_000);
+
+    // when
+    let res = terminate(&mut engine, &CALLER);
+
+    // then
+    assert_eq!(
+        res,
+        TerminationResult::Terminated {
+            value: 2_000_000,
+            beneficiary: CALLER.to_vec(),
+        }
+    );
+    assert_eq!(engine.get_balance(CALLER.to_vec()), Ok(2_000_000));
+}
+
+#[test]
+fn terminate_reports_rejected_transfer() {
+    // given
+    let mut engine = Engine::new();
+    let ed = minimum_balance(&mut engine);
+    engine.set_callee(CALLEE.to_vec());
+    engine.set_balance(CALLEE.to_vec(), ed - 1);
+
+    // when
+    let res = terminate(&mut engine, &CALLER);
+
+    // then
+    assert_eq!(
+        res,
+        TerminationResult::TransferFailed {
+            value: ed - 1,
+            beneficiary: CALLER.to_vec(),
+        }
+    );
+    assert_eq!(engine.get_balance(CALLER.to_vec()).ok(), None);
+}
+
+/// Writes to its storage and terminates in favor of `CALLER`.
+fn terminating_call(engine: &mut Engine) {
+    engine
+        .set_storage(&[0x42; 32], &[0x05_u8; 5])
+        .expect("storing must succeed");
+    engine.terminate(&CALLER)
+}
+
+#[test]
+fn terminate_removes_contract() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, terminating_call);
+    engine.set_callee(vec![1; 32]);
+    engine.set_balance(CALLEE.to_vec(), 2_000_000);
+    assert!(engine.is_contract(&CALLEE));
+
+    // when
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLEE,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut get_buffer()[..],
+    );
+
+    // then
+    assert_eq!(res, Ok(0));
+    assert!(!engine.is_contract(&CALLEE));
+    assert!(!engine
+        .database
+        .deployed_contracts()
+        .contains(&AccountId::from_bytes(&CALLEE)));
+    assert_eq!(engine.iter_contract_storage(&CALLEE).count(), 0);
+    assert_eq!(engine.get_balance(CALLER.to_vec()), Ok(2_000_000));
+    assert_eq!(engine.get_callee(), vec![1; 32]);
+}
+
+/// Mints the encoded amount to `CALLER`, failing after minting for amounts above
+/// `5_000_000`.
+fn mint_dispatchable(engine: &mut Engine, mut args: &[u8]) -> Result<(), Error> {
+    let amount: u128 = scale::Decode::decode(&mut args).map_err(|_| Error::Unknown)?;
+    engine.mint(&CALLER, amount);
+    if amount > 5_000_000 {
+        return Err(Error::Unknown)
+    }
+    Ok(())
+}
+
+#[test]
+fn call_runtime_dispatches_to_registered_call() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    engine.register_runtime_call(4, 2, mint_dispatchable);
+    let call = |pallet_index: u8, call_index: u8, amount: u128| {
+        let mut call = vec![pallet_index, call_index];
+        call.extend(scale::Encode::encode(&amount));
+        call
+    };
+
+    // when
+    let res = engine.call_runtime(&call(4, 2, 1_000_000));
+
+    // then
+    assert_eq!(res, Ok(()));
+    assert_eq!(engine.get_balance(CALLER.to_vec()), Ok(1_000_000));
+    assert_eq!(
+        engine.call_runtime(&call(4, 3, 1_000_000)),
+        Err(Error::CallRuntimeFailed)
+    );
+    assert_eq!(engine.call_runtime(&[4]), Err(Error::CallRuntimeFailed));
+    assert_eq!(
+        engine.call_runtime(&call(4, 2, 10_000_000)),
+        Err(Error::CallRuntimeFailed)
+    );
+    assert_eq!(engine.get_balance(CALLER.to_vec()), Ok(1_000_000));
+}
+
+#[test]
+fn storage_deposit_is_charged_and_refunded() {
+    // given
+    let mut engine = Engine::new();
+    engine.chain_spec.storage_deposit_per_byte = 10;
+    engine.set_caller(CALLER.to_vec());
+    engine.set_callee(CALLEE.to_vec());
+    engine.set_balance(CALLER.to_vec(), 50_000);
+    let key = &[0x42; 32];
+
+    // when
+    let res = engine.set_storage(key, &[0x05_u8; 1024]);
+
+    // then
+    assert_eq!(res, Ok(None));
+    assert_eq!(engine.storage_deposit(&CALLEE), 10_240);
+    assert_eq!(engine.get_balance(CALLER.to_vec()), Ok(39_760));
+    assert_eq!(engine.total_issuance(), 50_000);
+
+    // when
+    let _ = engine.set_storage(key, &[0x05_u8; 24]);
+    let _ = engine.clear_storage(key);
+
+    // then
+    assert_eq!(engine.storage_deposit(&CALLEE), 0);
+    assert_eq!(engine.get_balance(CALLER.to_vec()), Ok(50_000));
+}
+
+#[test]
+fn storage_deposit_must_be_covered_by_caller() {
+    // given
+    let mut engine = Engine::new();
+    engine.chain_spec.storage_deposit_per_byte = 10;
+    engine.set_caller(CALLER.to_vec());
+    engine.set_callee(CALLEE.to_vec());
+    engine.set_balance(CALLER.to_vec(), 100);
+    let key = &[0x42; 32];
+
+    // when
+    let res = engine.set_storage(key, &[0x05_u8; 11]);
+
+    // then
+    assert_eq!(res, Err(Error::TransferFailed));
+    assert_eq!(engine.contains_storage(key), None);
+    assert_eq!(engine.storage_deposit(&CALLEE), 0);
+    assert_eq!(engine.get_balance(CALLER.to_vec()), Ok(100));
+}
+
+/// Writes to its storage and returns its input with the return flags taken from
+/// the first input byte.
+fn returning_call(engine: &mut Engine) {
+    let input = engine.exec_context.input.clone();
+    engine
+        .set_storage(&[0x42; 32], &[0x05_u8; 5])
+        .expect("storing must succeed");
+    engine.set_return(u32::from(input[0]), &input[..]);
+}
+
+/// Calls `CALLEE` running `returning_call` with `input`.
+///
+/// Returns the result and the output of the call.
+fn call_returning(engine: &mut Engine, input: &[u8]) -> (Result<u32, Error>, [u8; 4]) {
+    let mut output = [0; 4];
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLEE,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        input,
+        &mut &mut output[..],
+    );
+    (res, output)
+}
+
+#[test]
+fn returning_without_revert_flag_succeeds() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, returning_call);
+    engine.set_callee(vec![1; 32]);
+
+    // when
+    let (res, output) = call_returning(&mut engine, &[0, 1, 2, 3]);
+
+    // then
+    assert_eq!(res, Ok(0));
+    assert_eq!(output, [0, 1, 2, 3]);
+    assert_eq!(engine.iter_contract_storage(&CALLEE).count(), 1);
+}
+
+#[test]
+fn call_returns_the_return_flags_of_the_callee() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, returning_call);
+    engine.set_callee(vec![1; 32]);
+
+    // when
+    let (res, output) = call_returning(&mut engine, &[2, 1, 2, 3]);
+
+    // then
+    assert_eq!(res, Ok(2));
+    assert_eq!(output, [2, 1, 2, 3]);
+}
+
+/// Sets its input as output, then traps.
+fn returning_then_trapping_call(engine: &mut Engine) {
+    let input = engine.exec_context.input.clone();
+    engine.set_return(0, &input[..]);
+    panic!("contract trapped");
+}
+
+#[test]
+fn trapping_callee_discards_output() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, returning_then_trapping_call);
+    engine.set_callee(vec![1; 32]);
+
+    // when
+    let (res, output) = call_returning(&mut engine, &[1, 2, 3, 4]);
+
+    // then
+    assert_eq!(res, Err(Error::CalleeTrapped));
+    assert_eq!(output, [0; 4]);
+}
+
+#[test]
+fn returning_with_revert_flag_reverts_but_keeps_output() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLEE, &[2; 32], noop, returning_call);
+    engine.set_callee(vec![1; 32]);
+
+    // when
+    let (res, output) = call_returning(&mut engine, &[1, 2, 3, 4]);
+
+    // then
+    assert_eq!(res, Err(Error::CalleeReverted));
+    assert_eq!(output, [1, 2, 3, 4]);
+    assert_eq!(engine.iter_contract_storage(&CALLEE).count(), 0);
+    assert_eq!(engine.get_callee(), vec![1; 32]);
+}
+
+#[test]
+fn caller_round_trips_for_all_address_lengths() {
+    for address_len in [20, 32] {
+        // given
+        let mut engine = Engine::new();
+        engine.chain_spec.address_len = address_len;
+        let account_id = vec![1; address_len];
+        engine.set_caller(account_id.clone());
+        engine.set_callee(account_id.clone());
+
+        // when
+        let mut caller = get_buffer();
+        engine.caller(&mut &mut caller[..]);
+        let mut address = get_buffer();
+        engine.address(&mut &mut address[..]);
+
+        // then
+        assert_eq!(&caller[..address_len], &account_id[..]);
+        assert_eq!(&address[..address_len], &account_id[..]);
+    }
+}
+
+#[test]
+fn instantiate_derives_addresses_of_configured_length() {
+    // given
+    let mut engine = Engine::new();
+    engine.chain_spec.address_len = 20;
+    let code_hash = [2; 32];
+    engine.register_contract(&[3; 20], &code_hash, echo_deploy, noop);
+    engine.set_callee(vec![1; 20]);
+
+    // when
+    let mut address = [0; 32];
+    let res = engine.instantiate(
+        &code_hash,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut address[..],
+        &mut &mut get_buffer()[..],
+        &[],
+    );
+
+    // then
+    assert_eq!(res, Ok(()));
+    assert_eq!(address[20..], [0; 12]);
+    assert!(engine.is_contract(&address[..20]));
+}
+
+#[test]
+#[should_panic(
+    expected = "the caller address has 32 bytes, but the addresses of the chain have 20 bytes"
+)]
+fn caller_must_match_address_length() {
+    let mut engine = Engine::new();
+    engine.chain_spec.address_len = 20;
+    engine.set_caller(vec![1; 32]);
+    engine.caller(&mut &mut get_buffer()[..]);
+}
+
+/// The `(caller, callee, value_transferred)` of a frame of the call stack.
+type RecordedFrame = (Option<Vec<u8>>, Option<Vec<u8>>, u128);
+
+/// Returns the encoded `(caller, callee, value_transferred)` of every frame of the
+/// call stack.
+fn call_stack_recording_call(engine: &mut Engine) {
+    let frames = engine
+        .call_stack()
+        .into_iter()
+        .map(|frame| {
+            (
+                frame.caller.map(|caller| caller.as_bytes().to_vec()),
+                frame.callee.map(|callee| callee.as_bytes().to_vec()),
+                frame.value_transferred,
+            )
+        })
+        .collect::<Vec<_>>();
+    engine.set_return(0, &scale::Encode::encode(&frames));
+}
+
+/// Calls `CALLEE` transferring `1_000_000` and returns what `CALLEE` returned.
+fn value_forwarding_call(engine: &mut Engine) {
+    let mut output = get_buffer();
+    engine
+        .call(
+            CallFlags::new(),
+            &CALLEE,
+            Weight::zero(),
+            &scale::Encode::encode(&1_000_000u128),
+            &[],
+            &mut &mut output[..],
+        )
+        .expect("call into `CALLEE` must succeed");
+    engine.set_return(0, &output[..]);
+}
+
+#[test]
+fn call_stack_reflects_nested_calls() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLER, &[1; 32], noop, value_forwarding_call);
+    engine.register_contract(&CALLEE, &[2; 32], noop, call_stack_recording_call);
+    engine.set_caller(vec![1; 32]);
+    engine.set_callee(vec![2; 32]);
+    engine.set_balance(vec![2; 32], 1_000_000);
+    engine.set_balance(CALLER.to_vec(), 2_000_000);
+
+    // when
+    let mut output = get_buffer();
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLER,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut output[..],
+    );
+
+    // then
+    assert_eq!(res, Ok(0));
+    let frames: Vec<RecordedFrame> =
+        scale::Decode::decode(&mut &output[..]).expect("decoding failed");
+    assert_eq!(
+        frames,
+        vec![
+            (Some(vec![1; 32]), Some(vec![2; 32]), 0),
+            (Some(vec![2; 32]), Some(CALLER.to_vec()), 0),
+            (Some(CALLER.to_vec()), Some(CALLEE.to_vec()), 1_000_000),
+        ]
+    );
+    assert_eq!(engine.call_stack().len(), 1);
+}
+
+#[test]
+fn popping_a_context_restores_the_previous_context() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(CALLER.to_vec());
+    engine.set_callee(CALLEE.to_vec());
+    let mut nested = crate::exec_context::ExecContext::new();
+    nested.caller = Some(AccountId::from_bytes(&CALLEE));
+    nested.callee = Some(AccountId::from_bytes(&[12; 32]));
+    nested.value_transferred = 5;
+
+    // when
+    engine.push_context(nested);
+
+    // then
+    let mut caller = get_buffer();
+    engine.caller(&mut &mut caller[..]);
+    assert_eq!(&caller[..32], &CALLEE[..]);
+    let mut address = get_buffer();
+    engine.address(&mut &mut address[..]);
+    assert_eq!(&address[..32], &[12; 32][..]);
+    assert_eq!(engine.call_stack().len(), 2);
+
+    // when
+    let popped = engine.pop_context().expect("a context has been pushed");
+
+    // then
+    assert_eq!(popped.value_transferred, 5);
+    engine.caller(&mut &mut caller[..]);
+    assert_eq!(&caller[..32], &CALLER[..]);
+    engine.address(&mut &mut address[..]);
+    assert_eq!(&address[..32], &CALLEE[..]);
+    assert_eq!(engine.call_stack().len(), 1);
+}
+
+#[test]
+fn popping_without_a_pushed_context_keeps_the_context() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(CALLEE.to_vec());
+
+    // when
+    let popped = engine.pop_context();
+
+    // then
+    assert!(popped.is_none());
+    assert_eq!(engine.get_callee(), CALLEE.to_vec());
+}
+
+/// Returns the gas left as seen by the callee.
+fn gas_left_call(engine: &mut Engine) {
+    let mut output = get_buffer();
+    engine.gas_left(&mut &mut output[..]);
+    engine.set_return(0, &output[..8]);
+}
+
+/// Calls host functions until the callee runs out of gas.
+fn gas_burning_call(engine: &mut Engine) {
+    let mut output = get_buffer();
+    loop {
+        engine.minimum_balance(&mut &mut output[..]);
+    }
+}
+
+/// Calls `CALLEE` with a gas limit of `gas_limit` and returns the gas left as
+/// seen by the callee.
+fn call_gas_left(engine: &mut Engine, gas_limit: u64) -> u64 {
+    let mut output = get_buffer();
+    engine
+        .call(
+            CallFlags::new(),
+            &CALLEE,
+            Weight::from(gas_limit),
+            &scale::Encode::encode(&0u128),
+            &[],
+            &mut &mut output[..],
+        )
+        .expect("call into `CALLEE` must succeed");
+    scale::Decode::decode(&mut &output[..]).expect("decoding gas left failed")
+}
+
+#[test]
+fn call_partitions_gas_between_caller_and_callee() {
+    // given
+    let mut engine = Engine::new_with_gas_model(GasModel::new(1));
+    engine.set_gas_limit(100);
+    engine.set_callee(CALLER.to_vec());
+    engine.register_contract(&CALLEE, &[1; 32], noop, gas_left_call);
+
+    // when
+    let gas_left = call_gas_left(&mut engine, 10);
+
+    // then
+    // `call` and `gas_left` have been charged before the callee read the gas left
+    assert_eq!(gas_left, 9);
+    // `call`, `gas_left` and `set_return`
+    assert_eq!(engine.gas_consumed(), 3);
+}
+
+#[test]
+fn call_with_zero_gas_limit_passes_all_gas_left() {
+    // given
+    let mut engine = Engine::new_with_gas_model(GasModel::new(1));
+    engine.set_gas_limit(100);
+    engine.set_callee(CALLER.to_vec());
+    engine.register_contract(&CALLEE, &[1; 32], noop, gas_left_call);
+
+    // when
+    let gas_left = call_gas_left(&mut engine, 0);
+
+    // then
+    assert_eq!(gas_left, 98);
+    assert_eq!(call_gas_left(&mut engine, 1000), 95);
+}
+
+#[test]
+fn callee_running_out_of_gas_traps() {
+    // given
+    let mut engine = Engine::new_with_gas_model(GasModel::new(1));
+    engine.set_gas_limit(100);
+    engine.set_callee(CALLER.to_vec());
+    engine.register_contract(&CALLEE, &[1; 32], noop, gas_burning_call);
+
+    // when
+    let mut output = get_buffer();
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLEE,
+        Weight::from(10),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut output[..],
+    );
+
+    // then
+    assert_eq!(res, Err(Error::CalleeTrapped));
+    // the `call` and the whole budget of the callee have been consumed
+    assert_eq!(engine.gas_consumed(), 11);
+    engine.gas_left(&mut &mut output[..]);
+    assert_eq!(scale::Decode::decode(&mut &output[..]), Ok(88u64));
+}
+
+#[test]
+fn call_flags_have_the_bit_layout_of_the_contracts_pallet() {
+    assert_eq!(CallFlags::new().bits(), 0);
+    assert_eq!(CallFlags::new().forward_input().bits(), 0b0001);
+    assert_eq!(CallFlags::new().clone_input().bits(), 0b0010);
+    assert_eq!(CallFlags::new().tail_call().bits(), 0b0100);
+    assert_eq!(CallFlags::new().allow_reentry().bits(), 0b1000);
+    assert_eq!(
+        CallFlags::new().clone_input().tail_call().allow_reentry(),
+        CallFlags::from_bits(0b1110)
+    );
+}
+
+/// Calls `CALLER` without allowing to be re-entered.
+fn reentering_call(engine: &mut Engine) {
+    let mut output = get_buffer();
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLER,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut output[..],
+    );
+    assert_eq!(res, Err(Error::CalleeTrapped));
+}
+
+#[test]
+fn denied_reentrancy_records_trace() {
+    // given
+    let mut engine = Engine::new();
+    engine.register_contract(&CALLER, &[1; 32], noop, noop);
+    engine.register_contract(&CALLEE, &[2; 32], noop, reentering_call);
+    engine.set_callee(CALLER.to_vec());
+    assert_eq!(engine.last_reentrancy_trace(), None);
+
+    // when
+    let mut output = get_buffer();
+    let res = engine.call(
+        CallFlags::new(),
+        &CALLEE,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut output[..],
+    );
+
+    // then
+    assert_eq!(res, Ok(0));
+    assert_eq!(
+        engine.last_reentrancy_trace(),
+        Some(vec![
+            AccountId::from_bytes(&CALLER),
+            AccountId::from_bytes(&CALLEE),
+            AccountId::from_bytes(&CALLER),
+        ])
+    );
+}
+
+/// Stores a value, then reverts with `[1, 2, 3]` as output.
+fn reverting_call(engine: &mut Engine) {
+    engine
+        .set_storage(&[42], &[7])
+        .expect("storing must succeed");
+    engine.set_return(1, &[1, 2, 3]);
+}
+
+/// Panics with a message.
+fn panicking_call(_engine: &mut Engine) {
+    panic!("the contract panicked")
+}
+
+#[test]
+fn execute_entry_point_returns_flags_and_data() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(CALLER.to_vec());
+    engine.set_callee(CALLEE.to_vec());
+
+    // when
+    let reverted = engine.execute_entry_point(reverting_call, Vec::new());
+    let returned = engine.execute_entry_point(returning_call, vec![0, 5]);
+
+    // then
+    assert_eq!(
+        reverted,
+        Ok(ExecReturn {
+            flags: 1,
+            data: vec![1, 2, 3],
+        })
+    );
+    assert!(reverted
+        .expect("the contract must have returned")
+        .did_revert());
+    assert_eq!(engine.contains_storage(&[42]), None);
+    assert_eq!(
+        returned,
+        Ok(ExecReturn {
+            flags: 0,
+            data: vec![0, 5],
+        })
+    );
+    assert_eq!(engine.contains_storage(&[0x42; 32]), Some(5));
+}
+
+#[test]
+fn execute_entry_point_reports_traps() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(CALLER.to_vec());
+    engine.set_callee(CALLEE.to_vec());
+
+    // when
+    let res = engine.execute_entry_point(panicking_call, Vec::new());
+
+    // then
+    assert_eq!(
+        res,
+        Err(TrapReason::Panic(String::from("the contract panicked")))
+    );
+}
+
+/// Stores a marker in the storage of the contract.
+fn storing_deploy(engine: &mut Engine) {
+    engine
+        .set_storage(&[1], &[1])
+        .expect("storing must succeed");
+}
+
+#[test]
+fn registered_contracts_can_be_deployed_and_called() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(CALLER.to_vec());
+    engine.set_callee(CALLER.to_vec());
+    engine.register_contract(&CALLEE, &[2; 32], storing_deploy, callee_call);
+
+    // when
+    let deployed = engine.run_deploy(&CALLEE);
+    let called = engine.run_call(&CALLEE, scale::Encode::encode(&21u32));
+
+    // then
+    assert_eq!(
+        deployed,
+        Ok(ExecReturn {
+            flags: 0,
+            data: Vec::new(),
+        })
+    );
+    assert_eq!(
+        engine.database.get_from_contract_storage(&CALLEE, &[1]),
+        Some(&vec![1])
+    );
+    assert_eq!(
+        called.map(|exec_return| exec_return.data),
+        Ok(scale::Encode::encode(&42u32))
+    );
+    assert_eq!(engine.get_callee(), CALLER.to_vec());
+}
+
+/// Routes its input by selector: `0x00000001` returns the sum of two `u32`s,
+/// `0x00000002` doubles a `u32`.
+fn selector_routing_call(engine: &mut Engine) {
+    let input = engine.exec_context.input.clone();
+    let (selector, mut args) = input.split_at(4);
+    let output = match selector {
+        [0, 0, 0, 1] => {
+            let (a, b) = <(u32, u32) as scale::Decode>::decode(&mut args)
+                .expect("input must be two encoded `u32`s");
+            a + b
+        }
+        [0, 0, 0, 2] => {
+            <u32 as scale::Decode>::decode(&mut args)
+                .expect("input must be an encoded `u32`")
+                * 2
+        }
+        _ => panic!("unknown selector {selector:?}"),
+    };
+    engine.exec_context.output = scale::Encode::encode(&output);
+}
+
+#[test]
+fn messages_can_be_invoked_by_selector() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(CALLER.to_vec());
+    engine.register_contract(
+        &CALLEE,
+        &[2; 32],
+        selector_routing_call,
+        selector_routing_call,
+    );
+
+    // when
+    let sum: Result<u32, _> = engine.invoke_message(&CALLEE, [0, 0, 0, 1], &(3u32, 4u32));
+    let doubled: Result<u32, _> =
+        engine.invoke_constructor(&CALLEE, [0, 0, 0, 2], &21u32);
+    let unknown: Result<u32, _> = engine.invoke_message(&CALLEE, [0, 0, 0, 3], &());
+
+    // then
+    assert_eq!(sum, Ok(7));
+    assert_eq!(doubled, Ok(42));
+    assert_eq!(
+        unknown,
+        Err(TrapReason::Panic(String::from(
+            "unknown selector [0, 0, 0, 3]"
+        )))
+    );
+}
+
+#[test]
+#[should_panic(expected = "no contract is registered at")]
+fn run_call_requires_a_registered_contract() {
+    let mut engine = Engine::new();
+    engine.set_caller(CALLER.to_vec());
+    let _ = engine.run_call(&CALLEE, Vec::new());
+}
+
+/// Derives the address of a contract from the first bytes of its salt.
+struct SaltAddressDeriver;
+
+impl AddressDeriver for SaltAddressDeriver {
+    fn derive(
+        &self,
+        _code_hash: &[u8],
+        _input: &[u8],
+        salt: &[u8],
+        _deployer: &[u8],
+    ) -> Vec<u8> {
+        let mut address = salt.to_vec();
+        address.resize(32, 0);
+        address
+    }
+}
+
+#[test]
+fn instantiate_uses_the_address_deriver() {
+    // given
+    let mut engine = Engine::new();
+    engine.address_deriver = Box::new(SaltAddressDeriver);
+    let code_hash = [2; 32];
+    engine.register_contract(&CALLEE, &code_hash, echo_deploy, noop);
+    engine.set_callee(vec![1; 32]);
+
+    // when
+    let mut address = [0; 32];
+    let res = engine.instantiate(
+        &code_hash,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut address[..],
+        &mut &mut get_buffer()[..],
+        &[9, 8, 7],
+    );
+
+    // then
+    assert_eq!(res, Ok(()));
+    let expected = SaltAddressDeriver.derive(&code_hash, &[], &[9, 8, 7], &[1; 32]);
+    assert_eq!(address.to_vec(), expected);
+    assert_eq!(&address[..4], &[9, 8, 7, 0]);
+    assert!(engine.is_contract(&address));
+}
+
+#[test]
+fn contracts_can_be_instantiated_from_uploaded_code() {
+    // given
+    let mut engine = Engine::new();
+    let code_hash = [2; 32];
+    engine.upload_code(&code_hash, echo_deploy, noop);
+    engine.set_callee(vec![1; 32]);
+    let instantiate = |engine: &mut Engine, code_hash: &[u8]| {
+        let mut address = [0; 32];
+        engine
+            .instantiate(
+                code_hash,
+                Weight::zero(),
+                &scale::Encode::encode(&0u128),
+                &[],
+                &mut &mut address[..],
+                &mut &mut get_buffer()[..],
+                &[],
+            )
+            .map(|()| address)
+    };
+
+    // then
+    assert!(engine.database.deployed_contracts().is_empty());
+    let address = instantiate(&mut engine, &code_hash).expect("code must be uploaded");
+    assert_eq!(
+        engine.database.get_code_hash(&address),
+        Some(code_hash.to_vec())
+    );
+    assert_eq!(instantiate(&mut engine, &[3; 32]), Err(Error::CodeNotFound));
+}
+
+#[test]
+fn only_unused_code_can_be_removed() {
+    // given
+    let mut engine = Engine::new();
+    engine.upload_code(&[2; 32], echo_deploy, noop);
+    engine.register_contract(&CALLEE, &[3; 32], echo_deploy, noop);
+
+    // when
+    let unused = engine.remove_code(&[2; 32]);
+    let in_use = engine.remove_code(&[3; 32]);
+
+    // then
+    assert_eq!(unused, Ok(()));
+    assert_eq!(
+        engine.remove_code(&[2; 32]),
+        Err(crate::Error::CodeNotFound)
+    );
+    assert_eq!(in_use, Err(crate::Error::CodeInUse));
+    assert!(engine.contracts.get(&[3; 32]).is_some());
+    engine.remove_account(&CALLEE);
+    assert_eq!(engine.remove_code(&[3; 32]), Ok(()));
+}
+
+#[test]
+fn instantiate_fails_at_an_account_marked_as_contract() {
+    // given
+    let mut engine = Engine::new();
+    engine.address_deriver = Box::new(SaltAddressDeriver);
+    let code_hash = [2; 32];
+    engine.register_contract(&CALLEE, &code_hash, echo_deploy, noop);
+    engine.set_callee(vec![1; 32]);
+    let occupied = SaltAddressDeriver.derive(&code_hash, &[], &[9, 8, 7], &[1; 32]);
+    engine.set_contract(occupied.clone());
+
+    // when
+    let res = engine.instantiate(
+        &code_hash,
+        Weight::zero(),
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut get_buffer()[..],
+        &mut &mut get_buffer()[..],
+        &[9, 8, 7],
+    );
+
+    // then
+    assert_eq!(res, Err(Error::DuplicateContract));
+    assert_eq!(engine.database.get_code_hash(&occupied), None);
+    assert!(engine.get_balance(occupied).is_err());
+}
@@ -26,6 +26,48 @@ pub type BlockTimestamp = u64;
 /// Same type as the `DefaultEnvironment::Balance` type.
 pub type Balance = u128;
 
+/// A two-dimensional weight, i.e. an amount of gas.
+///
+/// The engine meters the `ref_time` of contract executions only, the
+/// `proof_size` is accounted for when converting weights to fees.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Weight {
+    /// The computational time the weight accounts for.
+    pub ref_time: u64,
+    /// The size of the storage proof the weight accounts for.
+    pub proof_size: u64,
+}
+
+impl Weight {
+    /// Returns the weight of zero `ref_time` and `proof_size`.
+    pub const fn zero() -> Self {
+        Self::from_parts(0, 0)
+    }
+
+    /// Creates a new weight from its `ref_time` and `proof_size`.
+    pub const fn from_parts(ref_time: u64, proof_size: u64) -> Self {
+        Self {
+            ref_time,
+            proof_size,
+        }
+    }
+}
+
+/// Converts a one-dimensional amount of gas into a weight, the gas becomes the
+/// `ref_time` of the weight.
+impl From<u64> for Weight {
+    fn from(ref_time: u64) -> Self {
+        Self::from_parts(ref_time, 0)
+    }
+}
+
+/// Converts a weight into a one-dimensional amount of gas, i.e. its `ref_time`.
+impl From<Weight> for u64 {
+    fn from(weight: Weight) -> Self {
+        weight.ref_time
+    }
+}
+
 /// The Account Id type used by this crate.
 #[derive(Debug, From, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
@@ -41,6 +83,16 @@ impl AccountId {
     pub fn as_bytes(&self) -> &[u8] {
         &self.0[..]
     }
+
+    /// Derives a 32 byte `AccountId` from the BLAKE2 256-bit hash of the
+    /// human-readable `seed`.
+    ///
+    /// The same seed always yields the same `AccountId`.
+    pub fn from_seed(seed: &str) -> Self {
+        let mut output = [0; 32];
+        crate::hashing::blake2b_256(seed.as_bytes(), &mut output);
+        Self(output.to_vec())
+    }
 }
 
 /// Key into the database.
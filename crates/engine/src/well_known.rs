@@ -0,0 +1,74 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Well known accounts for use in tests.
+//!
+//! The accounts are derived from the names of the development accounts of
+//! Substrate via [`AccountId::from_seed`]. Unlike the development accounts they
+//! are no valid public keys, but they are stable across test runs.
+
+use crate::types::AccountId;
+
+/// Returns the account of Alice.
+pub fn alice() -> AccountId {
+    AccountId::from_seed("Alice")
+}
+
+/// Returns the account of Bob.
+pub fn bob() -> AccountId {
+    AccountId::from_seed("Bob")
+}
+
+/// Returns the account of Charlie.
+pub fn charlie() -> AccountId {
+    AccountId::from_seed("Charlie")
+}
+
+/// Returns the account of Dave.
+pub fn dave() -> AccountId {
+    AccountId::from_seed("Dave")
+}
+
+/// Returns the account of Eve.
+pub fn eve() -> AccountId {
+    AccountId::from_seed("Eve")
+}
+
+/// Returns the account of Ferdie.
+pub fn ferdie() -> AccountId {
+    AccountId::from_seed("Ferdie")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accounts_are_deterministic() {
+        assert_eq!(AccountId::from_seed("Alice"), AccountId::from_seed("Alice"));
+        assert_eq!(alice(), AccountId::from_seed("Alice"));
+        assert_eq!(alice().as_bytes().len(), 32);
+    }
+
+    #[test]
+    fn distinct_seeds_yield_distinct_accounts() {
+        let accounts = [alice(), bob(), charlie(), dave(), eve(), ferdie()];
+        for (i, a) in accounts.iter().enumerate() {
+            for b in &accounts[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+        assert_ne!(AccountId::from_seed("alice"), alice());
+    }
+}
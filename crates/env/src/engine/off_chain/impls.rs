@@ -118,8 +118,13 @@ impl From<ext::Error> for crate::Error {
             ext::Error::CodeNotFound => Self::CodeNotFound,
             ext::Error::NotCallable => Self::NotCallable,
             ext::Error::LoggingDisabled => Self::LoggingDisabled,
+            ext::Error::CallRuntimeFailed => Self::CallRuntimeFailed,
             ext::Error::EcdsaRecoveryFailed => Self::EcdsaRecoveryFailed,
             ext::Error::Sr25519VerifyFailed => Self::Sr25519VerifyFailed,
+            ext::Error::DuplicateContract => Self::DuplicateContract,
+            ext::Error::Ed25519VerifyFailed => Self::Ed25519VerifyFailed,
+            ext::Error::BufferTooSmall => Self::BufferTooSmall,
+            ext::Error::StorageExhausted => Self::StorageExhausted,
         }
     }
 }
@@ -174,14 +179,14 @@ impl EnvInstance {
     /// Returns the contract property value.
     fn get_property<T>(
         &mut self,
-        ext_fn: fn(engine: &Engine, output: &mut &mut [u8]),
+        ext_fn: fn(engine: &mut Engine, output: &mut &mut [u8]),
     ) -> Result<T>
     where
         T: scale::Decode,
     {
         let mut full_scope: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
         let full_scope = &mut &mut full_scope[..];
-        ext_fn(&self.engine, full_scope);
+        ext_fn(&mut self.engine, full_scope);
         scale::Decode::decode(&mut &full_scope[..]).map_err(Into::into)
     }
 }
@@ -194,7 +199,16 @@ impl EnvBackend for EnvInstance {
     {
         let mut v = vec![];
         Storable::encode(value, &mut v);
-        self.engine.set_storage(&key.encode(), &v[..])
+        match self.engine.set_storage(&key.encode(), &v[..]) {
+            Ok(old_len) => old_len,
+            Err(ext::Error::StorageExhausted) => {
+                panic!(
+                    "the contract storage would exceed `max_storage_bytes` of {} bytes",
+                    self.engine.chain_spec.max_storage_bytes
+                )
+            }
+            Err(error) => panic!("could not pay the storage deposit: {error:?}"),
+        }
     }
 
     fn get_contract_storage<K, R>(&mut self, key: &K) -> Result<Option<R>>
@@ -259,7 +273,8 @@ impl EnvBackend for EnvInstance {
     }
 
     fn debug_message(&mut self, message: &str) {
-        self.engine.debug_message(message)
+        // Like on-chain, the message has no effect if debug messages are disabled.
+        let _ = self.engine.debug_message(message);
     }
 
     fn hash_bytes<H>(&mut self, input: &[u8], output: &mut <H as HashOutput>::Type)
@@ -374,7 +389,10 @@ impl EnvBackend for EnvInstance {
         let mut output: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
 
         self.engine
-            .call_chain_extension(func_id, enc_input, &mut &mut output[..]);
+            .call_chain_extension(func_id, enc_input, &mut &mut output[..])
+            .unwrap_or_else(|error| {
+                panic!("Encountered unexpected missing chain extension method: {error:?}")
+            });
         let (status, out): (u32, Vec<u8>) = scale::Decode::decode(&mut &output[..])
             .unwrap_or_else(|error| {
                 panic!("could not decode `call_chain_extension` output: {error:?}")
@@ -532,7 +550,7 @@ impl TypedEnvBackend for EnvInstance {
 
     fn weight_to_fee<E: Environment>(&mut self, gas: u64) -> E::Balance {
         let mut output: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
-        self.engine.weight_to_fee(gas, &mut &mut output[..]);
+        self.engine.weight_to_fee(gas.into(), &mut &mut output[..]);
         scale::Decode::decode(&mut &output[..]).unwrap_or_else(|error| {
             panic!("could not read `weight_to_fee` property: {error:?}")
         })
@@ -542,7 +560,7 @@ impl TypedEnvBackend for EnvInstance {
     where
         E: Environment,
     {
-        self.engine.is_contract(scale::Encode::encode(&account))
+        self.engine.is_contract(&scale::Encode::encode(&account))
     }
 
     fn caller_is_origin<E>(&mut self) -> bool
@@ -563,13 +581,17 @@ impl TypedEnvBackend for EnvInstance {
     where
         E: Environment,
     {
-        unimplemented!("off-chain environment does not support `own_code_hash`")
+        let mut output: [u8; 32] = [0; 32];
+        self.engine.own_code_hash(&mut &mut output[..])?;
+        let hash = scale::Decode::decode(&mut &output[..])?;
+        Ok(hash)
     }
 
-    fn call_runtime<E, Call>(&mut self, _call: &Call) -> Result<()>
+    fn call_runtime<E, Call>(&mut self, call: &Call) -> Result<()>
     where
         E: Environment,
+        Call: scale::Encode,
     {
-        unimplemented!("off-chain environment does not support `call_runtime`")
+        self.engine.call_runtime(&call.encode()).map_err(Into::into)
     }
 }
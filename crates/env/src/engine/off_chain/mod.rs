@@ -59,6 +59,14 @@ pub enum OffChainError {
     UninitializedExecutionContext,
     #[from(ignore)]
     UnregisteredChainExtension,
+    #[from(ignore)]
+    DecreasingBlockNumber,
+    #[from(ignore)]
+    DecreasingBlockTimestamp,
+    #[from(ignore)]
+    CodeNotFound,
+    #[from(ignore)]
+    CodeInUse,
 }
 
 /// Errors encountered upon interacting with the accounts database.
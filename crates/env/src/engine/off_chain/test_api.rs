@@ -23,7 +23,10 @@ use crate::{
     Result,
 };
 use core::fmt::Debug;
-use ink_engine::test_api::RecordedDebugMessages;
+use ink_engine::{
+    ext::TerminationResult,
+    test_api::RecordedDebugMessages,
+};
 use std::panic::UnwindSafe;
 
 pub use super::call_data::CallData;
@@ -98,6 +101,22 @@ where
     })
 }
 
+/// Registers `dispatchable` to be executed by `call_runtime` for runtime calls with
+/// the given `pallet_index` and `call_index`.
+///
+/// The dispatchable is invoked with the SCALE encoded arguments of the call.
+pub fn register_runtime_call(
+    pallet_index: u8,
+    call_index: u8,
+    dispatchable: ink_engine::ext::RuntimeCall,
+) {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .register_runtime_call(pallet_index, call_index, dispatchable);
+    })
+}
+
 /// Returns the contents of the past performed environmental debug messages in order.
 pub fn recorded_debug_messages() -> RecordedDebugMessages {
     <EnvInstance as OnInstance>::on_instance(|instance| {
@@ -172,7 +191,7 @@ where
     <EnvInstance as OnInstance>::on_instance(|instance| {
         instance
             .engine
-            .is_contract(scale::Encode::encode(&contract))
+            .is_contract(&scale::Encode::encode(&contract))
     })
 }
 
@@ -206,6 +225,10 @@ where
 ///
 /// Please note that the acting accounts should be set with [`set_caller()`] and
 /// [`set_callee()`] beforehand.
+///
+/// # Panics
+///
+/// If the caller does not have at least `value` free balance.
 pub fn set_value_transferred<T>(value: T::Balance)
 where
     T: Environment<Balance = u128>, // Just temporary for the MVP!
@@ -224,33 +247,7 @@ where
     T: Environment<Balance = u128>, // Just temporary for the MVP!
 {
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        let caller = instance
-            .engine
-            .exec_context
-            .caller
-            .as_ref()
-            .expect("no caller has been set")
-            .as_bytes()
-            .to_vec();
-
-        let caller_old_balance = instance
-            .engine
-            .get_balance(caller.clone())
-            .unwrap_or_default();
-
-        let callee = instance.engine.get_callee();
-        let contract_old_balance = instance
-            .engine
-            .get_balance(callee.clone())
-            .unwrap_or_default();
-
-        instance
-            .engine
-            .set_balance(caller, caller_old_balance - value);
-        instance
-            .engine
-            .set_balance(callee, contract_old_balance + value);
-        instance.engine.set_value_transferred(value);
+        instance.engine.transfer_in(value);
     });
 }
 
@@ -275,7 +272,10 @@ where
     T: Environment<Timestamp = u64>,
 {
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        instance.engine.set_block_timestamp(value);
+        instance
+            .engine
+            .set_block_timestamp(value)
+            .unwrap_or_else(|err| panic!("could not set block timestamp: {err:?}"));
     })
 }
 
@@ -285,7 +285,10 @@ where
     T: Environment<BlockNumber = u32>,
 {
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        instance.engine.set_block_number(value);
+        instance
+            .engine
+            .set_block_number(value)
+            .unwrap_or_else(|err| panic!("could not set block number: {err:?}"));
     })
 }
 
@@ -384,6 +387,12 @@ pub fn recorded_events() -> impl Iterator<Item = EmittedEvent> {
 ///   remaining value in the contract
 /// * `expected_value_transferred_to_beneficiary`: The value which should have been
 ///   transferred to the `expected_beneficiary`.
+///
+/// # Panics
+///
+/// If the contract did not terminate, or if the transfer of its remaining balance
+/// to the beneficiary was rejected.
+///
 /// # Usage
 ///
 /// ```no_compile
@@ -411,9 +420,18 @@ pub fn assert_contract_termination<T, F>(
     let encoded_input = value_any
         .downcast_ref::<Vec<u8>>()
         .expect("panic object can not be cast");
-    let (value_transferred, encoded_beneficiary): (T::Balance, Vec<u8>) =
+    let result: TerminationResult<T::Balance> =
         scale::Decode::decode(&mut &encoded_input[..])
             .unwrap_or_else(|err| panic!("input can not be decoded: {err}"));
+    let (value_transferred, encoded_beneficiary) = match result {
+        TerminationResult::Terminated { value, beneficiary } => (value, beneficiary),
+        TerminationResult::TransferFailed { value, .. } => {
+            panic!(
+                "contract termination failed: the transfer of {value:?} \
+                 to the beneficiary was rejected"
+            )
+        }
+    };
     let beneficiary =
         <T::AccountId as scale::Decode>::decode(&mut &encoded_beneficiary[..])
             .unwrap_or_else(|err| panic!("input can not be decoded: {err}"));
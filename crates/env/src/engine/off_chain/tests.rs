@@ -13,7 +13,13 @@
 // limitations under the License.
 
 use crate::{
-    engine::off_chain::impls::TopicsBuilder,
+    engine::{
+        off_chain::{
+            impls::TopicsBuilder,
+            EnvInstance,
+        },
+        OnInstance,
+    },
     event::TopicsBuilderBackend,
     Result,
 };
@@ -41,3 +47,70 @@ fn topics_builder() -> Result<()> {
         Ok(())
     })
 }
+
+#[test]
+fn engine_errors_map_to_dedicated_env_errors() {
+    use ink_engine::ext;
+
+    assert_eq!(
+        crate::Error::from(ext::Error::DuplicateContract),
+        crate::Error::DuplicateContract
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::Ed25519VerifyFailed),
+        crate::Error::Ed25519VerifyFailed
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::BufferTooSmall),
+        crate::Error::BufferTooSmall
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::StorageExhausted),
+        crate::Error::StorageExhausted
+    );
+}
+
+#[test]
+#[should_panic(
+    expected = "the contract storage would exceed `max_storage_bytes` of 8 bytes"
+)]
+fn set_contract_storage_panics_past_max_storage_bytes() {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        <EnvInstance as OnInstance>::on_instance(|instance| {
+            instance.engine.chain_spec.max_storage_bytes = 8;
+        });
+        crate::set_contract_storage(&1u32, &[0u8; 4]);
+
+        // when
+        crate::set_contract_storage(&2u32, &[0u8; 5]);
+
+        Ok(())
+    })
+    .unwrap()
+}
+
+#[test]
+fn own_code_hash_returns_code_hash_of_callee() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        assert_eq!(
+            crate::own_code_hash::<crate::DefaultEnvironment>(),
+            Err(crate::Error::CodeNotFound)
+        );
+        <EnvInstance as OnInstance>::on_instance(|instance| {
+            let callee = instance.engine.get_callee();
+            instance
+                .engine
+                .register_contract(&callee, &[7; 32], |_| (), |_| ());
+        });
+
+        // when
+        let code_hash = crate::own_code_hash::<crate::DefaultEnvironment>()?;
+
+        // then
+        assert_eq!(code_hash, ink_primitives::Hash::from([7; 32]));
+
+        Ok(())
+    })
+}
@@ -42,6 +42,14 @@ impl From<ink_engine::Error> for Error {
             ink_engine::Error::UnregisteredChainExtension => {
                 OffChainError::UnregisteredChainExtension
             }
+            ink_engine::Error::DecreasingBlockNumber => {
+                OffChainError::DecreasingBlockNumber
+            }
+            ink_engine::Error::DecreasingBlockTimestamp => {
+                OffChainError::DecreasingBlockTimestamp
+            }
+            ink_engine::Error::CodeNotFound => OffChainError::CodeNotFound,
+            ink_engine::Error::CodeInUse => OffChainError::CodeInUse,
         };
         Error::OffChain(e)
     }
@@ -53,6 +53,14 @@ pub enum Error {
     EcdsaRecoveryFailed,
     /// sr25519 signature verification failed.
     Sr25519VerifyFailed,
+    /// A contract already exists at the address derived for the instantiation.
+    DuplicateContract,
+    /// ed25519 signature verification failed.
+    Ed25519VerifyFailed,
+    /// The output buffer is too small for the value read from the environment.
+    BufferTooSmall,
+    /// The contract would store more bytes than the chain allows.
+    StorageExhausted,
 }
 
 /// A result of environmental operations.
@@ -346,15 +346,11 @@ impl CallForwarder<'_> {
     /// Generate the code for all ink! trait messages implemented by the trait call
     /// forwarder.
     fn generate_ink_trait_impl_messages(&self) -> TokenStream2 {
-        let messages =
-            self.trait_def
-                .trait_def
-                .item()
-                .iter_items()
-                .filter_map(|(item, _)| {
-                    item.filter_map_message()
-                        .map(|message| self.generate_ink_trait_impl_for_message(&message))
-                });
+        let messages = self
+            .trait_def
+            .trait_def
+            .messages()
+            .map(|(message, _)| self.generate_ink_trait_impl_for_message(&message));
         quote! {
             #( #messages )*
         }
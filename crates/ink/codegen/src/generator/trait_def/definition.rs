@@ -55,10 +55,8 @@ impl TraitDefinition<'_> {
         let attrs = item.attrs();
         let ident = item.ident();
         let messages = item
-            .iter_items()
-            .map(|(item, _)| item)
-            .flat_map(ir::InkTraitItem::filter_map_message)
-            .map(Self::generate_for_message);
+            .messages()
+            .map(|(message, _)| Self::generate_for_message(message));
         quote_spanned!(span =>
             #(#attrs)*
             pub trait #ident: ::ink::env::ContractEnv {
@@ -21,7 +21,9 @@
 
 use super::TraitDefinition;
 use crate::{
-    generator::{self,},
+    generator::{
+        self,
+    },
     traits::GenerateCode,
     EnforcedErrors,
 };
@@ -119,12 +121,13 @@ impl TraitRegistry<'_> {
 
     /// Generate the code for all ink! trait messages implemented by the trait registry.
     fn generate_registry_messages(&self) -> TokenStream2 {
-        let messages = self.trait_def.trait_def.item().iter_items().filter_map(
-            |(item, selector)| {
-                item.filter_map_message()
-                    .map(|message| self.generate_registry_for_message(&message, selector))
-            },
-        );
+        let messages = self
+            .trait_def
+            .trait_def
+            .messages()
+            .map(|(message, selector)| {
+                self.generate_registry_for_message(&message, selector)
+            });
         quote! {
             #( #messages )*
         }
@@ -301,13 +304,13 @@ impl TraitRegistry<'_> {
     /// ink! messages defined by the ink! trait definition.
     fn generate_info_for_trait_messages(&self) -> TokenStream2 {
         let span = self.span();
-        let message_impls = self.trait_def.trait_def.item().iter_items().filter_map(
-            |(trait_item, selector)| {
-                trait_item.filter_map_message().map(|message| {
+        let message_impls =
+            self.trait_def
+                .trait_def
+                .messages()
+                .map(|(message, selector)| {
                     self.generate_info_for_trait_for_message(&message, selector)
-                })
-            },
-        );
+                });
         quote_spanned!(span=>
             #( #message_impls )*
         )
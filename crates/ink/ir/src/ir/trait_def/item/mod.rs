@@ -105,6 +105,14 @@ impl InkItemTrait {
         IterInkTraitItems::new(self)
     }
 
+    /// Returns an iterator yielding the ink! messages of the ink! trait definition
+    /// together with their selectors.
+    pub fn messages(&self) -> impl Iterator<Item = (InkTraitMessage, Selector)> {
+        self.iter_items().filter_map(|(item, selector)| {
+            item.filter_map_message().map(|message| (message, selector))
+        })
+    }
+
     /// Analyses the properties of the ink! trait definition.
     ///
     /// # Errors
@@ -300,6 +308,7 @@ impl InkItemTrait {
     /// # Errors
     ///
     /// - If the message has no `&self` or `&mut self` receiver.
+    /// - If the message takes `self` by value.
     fn analyse_trait_message(message: &syn::TraitItemFn) -> Result<()> {
         InkTraitMessage::extract_attributes(message.span(), &message.attrs)?;
         match message.sig.receiver() {
@@ -309,6 +318,14 @@ impl InkItemTrait {
                     "missing `&self` or `&mut self` receiver for ink! message",
                 ))
             }
+            Some(receiver)
+                if receiver.reference.is_none() && receiver.colon_token.is_none() =>
+            {
+                return Err(format_err_spanned!(
+                    receiver,
+                    "ink! message must not consume `self`, use `&self` or `&mut self` instead",
+                ))
+            }
             Some(receiver) => {
                 if receiver.reference.is_none() {
                     return Err(format_err_spanned!(
@@ -379,8 +396,10 @@ impl InkItemTrait {
                 use crate::error::ExtError as _;
                 return Err(format_err_spanned!(
                     ident,
-                    "encountered duplicate selector ({:x?}) in the same ink! trait definition",
+                    "encountered duplicate selector ({:x?}) of `{}` and `{}` in the same ink! trait definition",
                     selector.to_bytes(),
+                    duplicate_selector,
+                    ident,
                 ).into_combine(format_err_spanned!(
                     duplicate_selector,
                     "first ink! trait constructor or message with same selector found here",
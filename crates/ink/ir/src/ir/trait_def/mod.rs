@@ -28,6 +28,7 @@ pub use self::{
     },
 };
 use super::attrs::InkAttribute;
+use crate::Selector;
 use proc_macro2::TokenStream as TokenStream2;
 use syn::Result;
 
@@ -69,4 +70,10 @@ impl InkTraitDefinition {
     pub fn item(&self) -> &InkItemTrait {
         &self.item
     }
+
+    /// Returns an iterator yielding the ink! messages of the ink! trait definition
+    /// together with their selectors.
+    pub fn messages(&self) -> impl Iterator<Item = (InkTraitMessage, Selector)> {
+        self.item.messages()
+    }
 }
@@ -286,12 +286,26 @@ fn trait_def_containing_invalid_message_is_denied() {
         }
     );
     assert_ink_trait_eq_err!(
-        error: "self receiver of ink! message must be `&self` or `&mut self`",
+        error: "ink! message must not consume `self`, use `&self` or `&mut self` instead",
         pub trait MyTrait {
             #[ink(message)]
             fn does_not_return_self(self);
         }
     );
+    assert_ink_trait_eq_err!(
+        error: "ink! message must not consume `self`, use `&self` or `&mut self` instead",
+        pub trait MyTrait {
+            #[ink(message)]
+            fn does_not_return_self(mut self);
+        }
+    );
+    assert_ink_trait_eq_err!(
+        error: "self receiver of ink! message must be `&self` or `&mut self`",
+        pub trait MyTrait {
+            #[ink(message)]
+            fn does_not_return_self(self: Box<Self>);
+        }
+    );
 }
 
 #[test]
@@ -410,8 +424,8 @@ fn trait_def_with_everything_combined_ok() {
 #[test]
 fn trait_def_with_overlapping_selectors() {
     assert_ink_trait_eq_err!(
-        error: "encountered duplicate selector ([c0, de, ca, fe]) \
-                in the same ink! trait definition",
+        error: "encountered duplicate selector ([c0, de, ca, fe]) of `my_message` \
+                and `my_message_mut` in the same ink! trait definition",
         pub trait MyTrait {
             #[ink(message, selector = 0xC0DECAFE)]
             fn my_message(&self);
@@ -421,6 +435,46 @@ fn trait_def_with_overlapping_selectors() {
     );
 }
 
+#[test]
+fn trait_def_with_wildcard_selector_is_denied() {
+    assert_ink_trait_eq_err!(
+        error: "encountered conflicting ink! attribute argument",
+        pub trait MyTrait {
+            #[ink(message, selector = _)]
+            fn my_message(&self);
+        }
+    );
+}
+
+#[test]
+fn trait_def_with_selector_overlapping_composed_selector() {
+    let trait_ident = quote::format_ident!("MyTrait");
+    let composed = crate::ir::Selector::compose(
+        crate::ir::TraitPrefix::new(&trait_ident, None),
+        &quote::format_ident!("my_message"),
+    );
+    let selector = syn::LitInt::new(
+        &format!("{:#010X}", composed.into_be_u32()),
+        proc_macro2::Span::call_site(),
+    );
+    let result = <InkItemTrait as TryFrom<syn::ItemTrait>>::try_from(syn::parse_quote! {
+        pub trait #trait_ident {
+            #[ink(message)]
+            fn my_message(&self);
+            #[ink(message, selector = #selector)]
+            fn my_message_mut(&mut self);
+        }
+    });
+    assert_eq!(
+        result.map(|_| ()).map_err(|err| err.to_string()),
+        Err(format!(
+            "encountered duplicate selector ({:x?}) of `my_message` and \
+            `my_message_mut` in the same ink! trait definition",
+            composed.to_bytes(),
+        ))
+    );
+}
+
 #[test]
 fn iter_messages_works() {
     let ink_trait =
@@ -444,3 +498,109 @@ fn iter_messages_works() {
     let expected = vec!["message_1".to_string(), "message_2".to_string()];
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn messages_yields_messages_with_selectors() {
+    let trait_def = InkTraitDefinition::new(
+        quote::quote! {},
+        quote::quote! {
+            pub trait MyTrait {
+                #[ink(message, selector = 0xC0DECAFE)]
+                fn message_1(&self);
+                #[ink(message)]
+                fn message_2(&mut self);
+            }
+        },
+    )
+    .unwrap();
+    let actual = trait_def
+        .messages()
+        .map(|(message, selector)| (message.ident().to_string(), selector))
+        .collect::<Vec<_>>();
+    let trait_ident = quote::format_ident!("MyTrait");
+    let expected = vec![
+        (
+            "message_1".to_string(),
+            crate::Selector::from([0xC0, 0xDE, 0xCA, 0xFE]),
+        ),
+        (
+            "message_2".to_string(),
+            crate::Selector::compose(
+                crate::ir::TraitPrefix::new(&trait_ident, None),
+                &quote::format_ident!("message_2"),
+            ),
+        ),
+    ];
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn config_exposes_namespace() {
+    let trait_def = InkTraitDefinition::new(
+        quote::quote! { namespace = "my_namespace" },
+        quote::quote! {
+            pub trait MyTrait {
+                #[ink(message)]
+                fn message(&self);
+            }
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        trait_def.config().namespace().map(syn::LitStr::value),
+        Some("my_namespace".to_string()),
+    );
+    let trait_def = InkTraitDefinition::new(
+        quote::quote! {},
+        quote::quote! {
+            pub trait MyTrait {
+                #[ink(message)]
+                fn message(&self);
+            }
+        },
+    )
+    .unwrap();
+    assert_eq!(trait_def.config().namespace(), None);
+}
+
+#[test]
+fn namespace_is_folded_into_selectors() {
+    let selectors_with = |config: proc_macro2::TokenStream| {
+        InkTraitDefinition::new(
+            config,
+            quote::quote! {
+                pub trait MyTrait {
+                    #[ink(message)]
+                    fn message(&self);
+                }
+            },
+        )
+        .unwrap()
+        .messages()
+        .map(|(_, selector)| selector)
+        .collect::<Vec<_>>()
+    };
+    let trait_ident = quote::format_ident!("MyTrait");
+    let message_ident = quote::format_ident!("message");
+    let namespace = syn::LitStr::new("my_namespace", proc_macro2::Span::call_site());
+    let without_namespace = selectors_with(quote::quote! {});
+    let with_namespace = selectors_with(quote::quote! { namespace = "my_namespace" });
+    let with_other_namespace =
+        selectors_with(quote::quote! { namespace = "other_namespace" });
+    assert_eq!(
+        without_namespace,
+        vec![crate::Selector::compose(
+            crate::ir::TraitPrefix::new(&trait_ident, None),
+            &message_ident,
+        )],
+    );
+    assert_eq!(
+        with_namespace,
+        vec![crate::Selector::compose(
+            crate::ir::TraitPrefix::new(&trait_ident, Some(&namespace)),
+            &message_ident,
+        )],
+    );
+    assert_ne!(without_namespace, with_namespace);
+    assert_ne!(with_namespace, with_other_namespace);
+}
@@ -0,0 +1,7 @@
+#[ink::trait_definition]
+pub trait TraitDefinition {
+    #[ink(message)]
+    fn message(mut self);
+}
+
+fn main() {}
@@ -66,17 +66,17 @@ pub mod give_me {
         #[ink::test]
         fn transfer_works() {
             // given
-            let contract_balance = 100;
+            let contract_balance = 3_000_000;
             let accounts = default_accounts();
             let mut give_me = create_contract(contract_balance);
 
             // when
             set_sender(accounts.eve);
             set_balance(accounts.eve, 0);
-            give_me.give_me(80);
+            give_me.give_me(1_200_000);
 
             // then
-            assert_eq!(get_balance(accounts.eve), 80);
+            assert_eq!(get_balance(accounts.eve), 1_200_000);
         }
 
         #[ink::test]
@@ -136,6 +136,7 @@ pub mod give_me {
             // the `mock_transferred_value` as the value which the contract
             // will see as transferred to it.
             set_sender(accounts.eve);
+            set_balance(accounts.eve, 100);
             ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(13);
 
             // then